@@ -0,0 +1,163 @@
+use futures_util::future::{select, Either};
+use std::{future::Future, time::Duration};
+use tgbot::{
+    methods::{Method, SendChatAction},
+    types::{ChatAction, ChatId, Message},
+    Api, ExecuteError,
+};
+use tokio::time::delay_for;
+
+// Telegram clients show an action for 5 seconds or less
+const ACTION_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Shows a chat action while a method is being prepared, then executes it
+///
+/// The action is sent periodically until the future is resolved,
+/// so it stays visible during a long work.
+/// Telegram clients hide the action when a message is received.
+/// Errors occurred when sending the action are logged and ignored.
+///
+/// # Arguments
+///
+/// * api - Client used to send requests
+/// * chat_id - ID of a chat
+/// * action - Action to show
+/// * future - A future which produces a method to execute
+///
+/// # Example
+///
+/// ```
+/// use carapax::{methods::SendDocument, types::{ChatAction, InputFile}, with_chat_action, Api, ExecuteError};
+///
+/// async fn send_report(api: &Api, chat_id: i64) -> Result<(), ExecuteError> {
+///     with_chat_action(api, chat_id, ChatAction::UploadDocument, async {
+///         // generate a report here
+///         SendDocument::new(chat_id, InputFile::url("https://example.com/report.pdf"))
+///     })
+///     .await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn with_chat_action<C, F, M>(
+    api: &Api,
+    chat_id: C,
+    action: ChatAction,
+    future: F,
+) -> Result<Message, ExecuteError>
+where
+    C: Into<ChatId>,
+    F: Future<Output = M>,
+    M: Method<Response = Message>,
+{
+    run_with_chat_action(api, chat_id.into(), action, future, ACTION_INTERVAL).await
+}
+
+async fn run_with_chat_action<F, M>(
+    api: &Api,
+    chat_id: ChatId,
+    action: ChatAction,
+    future: F,
+    interval: Duration,
+) -> Result<Message, ExecuteError>
+where
+    F: Future<Output = M>,
+    M: Method<Response = Message>,
+{
+    let keep_alive = async {
+        loop {
+            if let Err(err) = api.execute(SendChatAction::new(chat_id.clone(), action)).await {
+                log::error!("Failed to send chat action: {}", err);
+            }
+            delay_for(interval).await;
+        }
+    };
+    futures_util::pin_mut!(future);
+    futures_util::pin_mut!(keep_alive);
+    let method = match select(future, keep_alive).await {
+        Either::Left((method, _)) => method,
+        Either::Right(((), _)) => unreachable!("chat action loop never ends"),
+    };
+    api.execute(method).await
+}
+
+/// Shows "typing..." while a method is being prepared, then executes it
+///
+/// See [with_chat_action()](fn.with_chat_action.html) for more information.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{methods::SendMessage, with_typing, Api, ExecuteError};
+///
+/// async fn send_answer(api: &Api, chat_id: i64) -> Result<(), ExecuteError> {
+///     with_typing(api, chat_id, async {
+///         // do a slow lookup here
+///         SendMessage::new(chat_id, "Answer")
+///     })
+///     .await?;
+///     Ok(())
+/// }
+/// ```
+pub async fn with_typing<C, F, M>(api: &Api, chat_id: C, future: F) -> Result<Message, ExecuteError>
+where
+    C: Into<ChatId>,
+    F: Future<Output = M>,
+    M: Method<Response = Message>,
+{
+    with_chat_action(api, chat_id, ChatAction::Typing, future).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ok_response, MockServer};
+    use serde_json::json;
+    use tgbot::methods::SendMessage;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn keep_action_alive() {
+        let mut server = MockServer::new(|method, _| match method {
+            "sendChatAction" => ok_response(json!(true)),
+            _ => ok_response(json!({
+                "message_id": 1,
+                "date": 0,
+                "from": {"id": 1, "is_bot": true, "first_name": "bot"},
+                "chat": {"id": 1, "type": "private", "first_name": "test"},
+                "text": "test"
+            })),
+        });
+        let api = server.api();
+        let (tx, rx) = oneshot::channel();
+        let future = async {
+            rx.await.unwrap();
+            SendMessage::new(1, "test")
+        };
+        let interval = Duration::from_millis(10);
+        let run = run_with_chat_action(&api, ChatId::from(1), ChatAction::Typing, future, interval);
+        let ready = async {
+            // action is sent again after interval
+            server.wait_requests(2).await;
+            tx.send(()).unwrap();
+        };
+        let (message, ()) = futures_util::future::join(run, ready).await;
+        assert_eq!(message.unwrap().id, 1);
+
+        let requests = server.requests();
+        let methods: Vec<&str> = requests.iter().map(|request| request.method.as_str()).collect();
+        assert_eq!(methods, vec!["sendChatAction", "sendChatAction", "sendMessage"]);
+        for request in &requests[..2] {
+            assert_eq!(request.body, json!({"chat_id": 1, "action": "typing"}));
+        }
+        assert_eq!(requests[2].body, json!({"chat_id": 1, "text": "test"}));
+    }
+
+    #[tokio::test]
+    async fn method_ready_immediately() {
+        let server = MockServer::with_error();
+        let result = with_typing(&server.api(), 1, async { SendMessage::new(1, "test") }).await;
+        assert!(matches!(result, Err(ExecuteError::Response(_))));
+        // no action is needed
+        assert_eq!(server.methods(), vec!["sendMessage"]);
+    }
+}
@@ -0,0 +1,481 @@
+use crate::core::{handler::Handler, result::HandlerResult};
+use async_trait::async_trait;
+use std::{error::Error, fmt};
+use tgbot::{
+    methods::SendMessage,
+    types::{ChatId, Command, Float, Integer, UserId},
+    Api,
+};
+
+/// Allows to parse arguments of a command into typed values
+///
+/// # Example
+///
+/// ```
+/// use carapax::{types::{Command, Integer}, handler, CommandArgsError, ParseCommandArgs};
+///
+/// #[handler(command = "/ban")]
+/// async fn handle_ban(_context: &(), command: Command) -> Result<(), CommandArgsError> {
+///     let (user_id, reason): (Integer, String) = command.parse_args()?;
+///     println!("Ban {} for {}", user_id, reason);
+///     Ok(())
+/// }
+/// ```
+pub trait ParseCommandArgs {
+    /// Parses arguments
+    ///
+    /// Returns an error when number of arguments does not match
+    /// or an argument could not be converted to a target type
+    fn parse_args<T>(&self) -> Result<T, CommandArgsError>
+    where
+        T: FromCommandArgs;
+}
+
+impl ParseCommandArgs for Command {
+    fn parse_args<T>(&self) -> Result<T, CommandArgsError>
+    where
+        T: FromCommandArgs,
+    {
+        T::from_command_args(self.get_args())
+    }
+}
+
+/// A list of typed command arguments
+///
+/// Implemented for tuples of [CommandArg](trait.CommandArg.html) up to 8 items
+pub trait FromCommandArgs: Sized {
+    /// Creates a value from raw arguments
+    fn from_command_args(args: &[String]) -> Result<Self, CommandArgsError>;
+}
+
+/// A single typed command argument
+pub trait CommandArg: Sized {
+    /// Human readable name of expected value, used in error messages
+    fn expected() -> &'static str;
+
+    /// Converts a raw argument
+    ///
+    /// Returns None when value is invalid
+    fn from_arg(value: &str) -> Option<Self>;
+
+    /// Returns a value for a missing argument
+    ///
+    /// Argument is required when None is returned (default)
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+impl CommandArg for String {
+    fn expected() -> &'static str {
+        "a string"
+    }
+
+    fn from_arg(value: &str) -> Option<Self> {
+        Some(String::from(value))
+    }
+}
+
+impl CommandArg for Integer {
+    fn expected() -> &'static str {
+        "an integer"
+    }
+
+    fn from_arg(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+impl CommandArg for Float {
+    fn expected() -> &'static str {
+        "a number"
+    }
+
+    fn from_arg(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+impl CommandArg for bool {
+    fn expected() -> &'static str {
+        "a boolean"
+    }
+
+    fn from_arg(value: &str) -> Option<Self> {
+        value.parse().ok()
+    }
+}
+
+/// Accepts either a numeric ID or `@username`
+///
+/// Leading `@` is stripped, so the username can be compared with `User::username`
+impl CommandArg for UserId {
+    fn expected() -> &'static str {
+        "a user ID or @username"
+    }
+
+    fn from_arg(value: &str) -> Option<Self> {
+        if let Some(username) = value.strip_prefix('@') {
+            if username.is_empty() {
+                None
+            } else {
+                Some(UserId::from(username))
+            }
+        } else {
+            value.parse::<Integer>().ok().map(UserId::from)
+        }
+    }
+}
+
+/// Accepts either a numeric ID or `@username`
+///
+/// Username is kept as is, so the value can be passed to API methods directly
+impl CommandArg for ChatId {
+    fn expected() -> &'static str {
+        "a chat ID or @username"
+    }
+
+    fn from_arg(value: &str) -> Option<Self> {
+        if value.starts_with('@') {
+            if value.len() > 1 {
+                Some(ChatId::from(value))
+            } else {
+                None
+            }
+        } else {
+            value.parse::<Integer>().ok().map(ChatId::from)
+        }
+    }
+}
+
+impl<T> CommandArg for Option<T>
+where
+    T: CommandArg,
+{
+    fn expected() -> &'static str {
+        T::expected()
+    }
+
+    fn from_arg(value: &str) -> Option<Self> {
+        T::from_arg(value).map(Some)
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+fn parse_arg<T>(args: &[String], idx: usize) -> Result<T, CommandArgsError>
+where
+    T: CommandArg,
+{
+    let position = idx + 1;
+    match args.get(idx) {
+        Some(value) => T::from_arg(value).ok_or_else(|| CommandArgsError::Invalid {
+            position,
+            expected: T::expected(),
+            value: value.clone(),
+        }),
+        None => T::from_missing().ok_or_else(|| CommandArgsError::Missing {
+            position,
+            expected: T::expected(),
+        }),
+    }
+}
+
+macro_rules! impl_from_command_args {
+    ($count:expr => $($name:ident: $idx:expr),+) => {
+        impl<$($name),+> FromCommandArgs for ($($name,)+)
+        where
+            $($name: CommandArg),+
+        {
+            fn from_command_args(args: &[String]) -> Result<Self, CommandArgsError> {
+                if args.len() > $count {
+                    return Err(CommandArgsError::Unexpected {
+                        expected: $count,
+                        actual: args.len(),
+                    });
+                }
+                Ok(($(parse_arg::<$name>(args, $idx)?,)+))
+            }
+        }
+    };
+}
+
+impl_from_command_args!(1 => A: 0);
+impl_from_command_args!(2 => A: 0, B: 1);
+impl_from_command_args!(3 => A: 0, B: 1, C: 2);
+impl_from_command_args!(4 => A: 0, B: 1, C: 2, D: 3);
+impl_from_command_args!(5 => A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_from_command_args!(6 => A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_from_command_args!(7 => A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_from_command_args!(8 => A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// An error when parsing command arguments
+///
+/// Positions start from 1, so the error can be shown to a user as is
+#[derive(Debug)]
+pub enum CommandArgsError {
+    /// Argument is required but not provided
+    Missing {
+        /// Position of the argument
+        position: usize,
+        /// Expected type
+        expected: &'static str,
+    },
+    /// Argument could not be converted to a target type
+    Invalid {
+        /// Position of the argument
+        position: usize,
+        /// Expected type
+        expected: &'static str,
+        /// Raw value
+        value: String,
+    },
+    /// Got more arguments than expected
+    Unexpected {
+        /// Max number of arguments
+        expected: usize,
+        /// Actual number of arguments
+        actual: usize,
+    },
+}
+
+impl Error for CommandArgsError {}
+
+impl fmt::Display for CommandArgsError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandArgsError::Missing { position, expected } => {
+                write!(out, "argument {} is missing, expected {}", position, expected)
+            }
+            CommandArgsError::Invalid { position, expected, .. } => {
+                write!(out, "argument {} must be {}", position, expected)
+            }
+            CommandArgsError::Unexpected { expected, actual } => {
+                write!(out, "expected at most {} arguments, got {}", expected, actual)
+            }
+        }
+    }
+}
+
+/// Replies with a usage string when a wrapped command handler fails to parse arguments
+///
+/// When the wrapped handler returns [CommandArgsError](enum.CommandArgsError.html),
+/// a reply with the error and the usage string is sent and propagation stops.
+/// Other results are returned as is.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{types::{Command, Integer}, handler, Api, CommandArgsError, Dispatcher, ParseCommandArgs, ReplyCommandUsage};
+///
+/// #[handler(command = "/ban")]
+/// async fn handle_ban(_api: &Api, command: Command) -> Result<(), CommandArgsError> {
+///     let (user_id, reason): (Integer, Option<String>) = command.parse_args()?;
+///     println!("Ban {} for {:?}", user_id, reason);
+///     Ok(())
+/// }
+///
+/// let api = Api::new("token").unwrap();
+/// let mut dispatcher = Dispatcher::new(api.clone());
+/// dispatcher.add_handler(ReplyCommandUsage::new(api, "/ban <user_id> [reason]", handle_ban));
+/// ```
+pub struct ReplyCommandUsage<H> {
+    api: Api,
+    usage: String,
+    handler: H,
+}
+
+impl<H> ReplyCommandUsage<H> {
+    /// Creates a new handler
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client used to send a reply
+    /// * usage - Describes arguments of a command
+    /// * handler - A command handler to wrap
+    pub fn new<U>(api: Api, usage: U, handler: H) -> Self
+    where
+        U: Into<String>,
+    {
+        Self {
+            api,
+            usage: usage.into(),
+            handler,
+        }
+    }
+}
+
+#[async_trait]
+impl<C, H> Handler<C> for ReplyCommandUsage<H>
+where
+    C: Send + Sync,
+    H: Handler<C, Input = Command> + Send,
+{
+    type Input = Command;
+    type Output = HandlerResult;
+
+    async fn handle(&mut self, context: &C, input: Self::Input) -> Self::Output {
+        let message = input.get_message();
+        let (chat_id, message_id) = (message.get_chat_id(), message.id);
+        let err = match self.handler.handle(context, input).await.into() {
+            HandlerResult::Error(err) => err,
+            result => return result,
+        };
+        let text = match err.downcast_ref::<CommandArgsError>() {
+            Some(args_err) => format!("{}\nUsage: {}", args_err, self.usage),
+            None => return HandlerResult::Error(err),
+        };
+        let method = SendMessage::new(chat_id, text).reply_to_message_id(message_id);
+        match self.api.execute(method).await {
+            Ok(_) => HandlerResult::Stop,
+            Err(err) => HandlerResult::error(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ok_response, MockServer};
+    use serde_json::json;
+    use std::convert::TryFrom;
+    use tgbot::types::Message;
+
+    fn create_command(text: &str) -> Command {
+        let len = text.split_whitespace().next().unwrap().len();
+        let message: Message = serde_json::from_value(serde_json::json!({
+            "message_id": 1111,
+            "date": 0,
+            "from": {"id": 1, "is_bot": false, "first_name": "test"},
+            "chat": {"id": 1, "type": "private", "first_name": "test"},
+            "text": text,
+            "entities": [
+                {"type": "bot_command", "offset": 0, "length": len}
+            ]
+        }))
+        .unwrap();
+        Command::try_from(message).unwrap()
+    }
+
+    #[test]
+    fn parse_args_ok() {
+        let command = create_command("/ban 12 'spam links'");
+        let (user_id, reason): (Integer, String) = command.parse_args().unwrap();
+        assert_eq!(user_id, 12);
+        assert_eq!(reason, "spam links");
+
+        let command = create_command("/ban @username");
+        let (user_id, reason): (UserId, Option<String>) = command.parse_args().unwrap();
+        assert_eq!(user_id, UserId::from("username"));
+        assert!(reason.is_none());
+
+        let command = create_command("/send @channel 1.5 true");
+        let (chat_id, value, flag): (ChatId, Float, bool) = command.parse_args().unwrap();
+        assert_eq!(chat_id, ChatId::from("@channel"));
+        assert!((value - 1.5).abs() < Float::EPSILON);
+        assert!(flag);
+    }
+
+    #[test]
+    fn parse_args_invalid() {
+        let command = create_command("/ban 12abc");
+        let err = command.parse_args::<(Integer,)>().unwrap_err();
+        match err {
+            CommandArgsError::Invalid {
+                position,
+                expected,
+                ref value,
+            } => {
+                assert_eq!(position, 1);
+                assert_eq!(expected, "an integer");
+                assert_eq!(value, "12abc");
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        assert_eq!(err.to_string(), "argument 1 must be an integer");
+    }
+
+    #[test]
+    fn parse_args_missing() {
+        let command = create_command("/ban 12");
+        let err = command.parse_args::<(Integer, UserId)>().unwrap_err();
+        match err {
+            CommandArgsError::Missing { position, expected } => {
+                assert_eq!(position, 2);
+                assert_eq!(expected, "a user ID or @username");
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        assert_eq!(
+            err.to_string(),
+            "argument 2 is missing, expected a user ID or @username"
+        );
+    }
+
+    #[test]
+    fn parse_args_unexpected() {
+        let command = create_command("/ban 12 spam links");
+        let err = command.parse_args::<(Integer, String)>().unwrap_err();
+        match err {
+            CommandArgsError::Unexpected { expected, actual } => {
+                assert_eq!(expected, 2);
+                assert_eq!(actual, 3);
+            }
+            err => panic!("Unexpected error: {:?}", err),
+        }
+        assert_eq!(err.to_string(), "expected at most 2 arguments, got 3");
+    }
+
+    struct BanHandler;
+
+    #[async_trait]
+    impl Handler<()> for BanHandler {
+        type Input = Command;
+        type Output = HandlerResult;
+
+        async fn handle(&mut self, _context: &(), input: Self::Input) -> Self::Output {
+            match input.parse_args::<(Integer, Option<String>)>() {
+                Ok((_, Some(reason))) if reason == "fail" => HandlerResult::error(fmt::Error),
+                Ok(_) => HandlerResult::Continue,
+                Err(err) => HandlerResult::error(err),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn reply_usage() {
+        let server = MockServer::new(|_, body| {
+            ok_response(json!({
+                "message_id": 2,
+                "date": 0,
+                "from": {"id": 2, "is_bot": true, "first_name": "bot"},
+                "chat": {"id": 1, "type": "private", "first_name": "test"},
+                "text": body["text"]
+            }))
+        });
+        let mut handler = ReplyCommandUsage::new(server.api(), "/ban <user_id> [reason]", BanHandler);
+
+        let result = handler.handle(&(), create_command("/ban 12abc")).await;
+        assert!(matches!(result, HandlerResult::Stop));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "sendMessage");
+        assert_eq!(
+            requests[0].body,
+            json!({
+                "chat_id": 1,
+                "text": "argument 1 must be an integer\nUsage: /ban <user_id> [reason]",
+                "reply_to_message_id": 1111
+            })
+        );
+
+        // other results are returned as is
+        let result = handler.handle(&(), create_command("/ban 12")).await;
+        assert!(matches!(result, HandlerResult::Continue));
+        let result = handler.handle(&(), create_command("/ban 12 fail")).await;
+        assert!(matches!(result, HandlerResult::Error(_)));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+/// A bounded cache which forgets values after a lifetime
+///
+/// Values are kept in insertion order, so expired and extra values
+/// are evicted from the front without scanning the whole cache.
+pub(crate) struct TtlCache<K, V> {
+    counter: u64,
+    // key => (value, created at, insertion counter)
+    values: HashMap<K, (V, Instant, u64)>,
+    // insertion counter => key
+    order: BTreeMap<u64, K>,
+}
+
+impl<K, V> Default for TtlCache<K, V> {
+    fn default() -> Self {
+        Self {
+            counter: 0,
+            values: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Returns a value if it is not expired
+    pub(crate) fn get<Q>(&self, key: &Q, ttl: Duration) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.values
+            .get(key)
+            .filter(|(_, created_at, _)| created_at.elapsed() < ttl)
+            .map(|(value, _, _)| value)
+    }
+
+    /// Inserts a value, evicts expired values and oldest values when capacity is exceeded
+    pub(crate) fn insert(&mut self, key: K, value: V, capacity: usize, ttl: Duration) {
+        self.remove(&key);
+        if capacity == 0 {
+            return;
+        }
+        self.counter += 1;
+        self.order.insert(self.counter, key.clone());
+        self.values.insert(key, (value, Instant::now(), self.counter));
+        while let Some(oldest) = self.order.values().next().cloned() {
+            let is_expired = self
+                .values
+                .get(&oldest)
+                .map(|(_, created_at, _)| created_at.elapsed() >= ttl)
+                .unwrap_or(true);
+            if !is_expired && self.values.len() <= capacity {
+                break;
+            }
+            self.remove(&oldest);
+        }
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some((_, _, inserted_at)) = self.values.remove(key) {
+            self.order.remove(&inserted_at);
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.values.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evict() {
+        let ttl = Duration::from_secs(60);
+        let mut cache = TtlCache::default();
+        cache.insert(String::from("first"), 1, 2, ttl);
+        cache.insert(String::from("second"), 2, 2, ttl);
+        // reinserted value becomes newest
+        cache.insert(String::from("first"), 3, 2, ttl);
+        cache.insert(String::from("third"), 4, 2, ttl);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("second", ttl).is_none());
+        assert_eq!(cache.get("first", ttl), Some(&3));
+        assert_eq!(cache.get("third", ttl), Some(&4));
+        assert!(cache.get("third", Duration::from_secs(0)).is_none());
+
+        // expired values are evicted on insert
+        cache.insert(String::from("fourth"), 5, 2, Duration::from_secs(0));
+        assert_eq!(cache.len(), 0);
+
+        cache.insert(String::from("first"), 1, 0, ttl);
+        assert_eq!(cache.len(), 0);
+    }
+}
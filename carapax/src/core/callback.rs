@@ -0,0 +1,243 @@
+use crate::core::{convert::TryFromUpdate, handler::Handler, result::HandlerResult};
+use async_trait::async_trait;
+use std::{
+    convert::Infallible,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tgbot::{
+    methods::AnswerCallbackQuery,
+    types::{CallbackQuery, Update, UpdateKind},
+    Api, ExecuteError,
+};
+
+/// Answers a callback query when wrapped handler did not answer it
+///
+/// Telegram clients show a progress indicator on a button until the query is answered,
+/// so this handler sends an empty answer after the wrapped handler has finished,
+/// unless the query was answered with [AnswerableCallbackQuery::answer()].
+///
+/// Updates of other kinds are skipped.
+///
+/// [AnswerableCallbackQuery::answer()]: struct.AnswerableCallbackQuery.html#method.answer
+///
+/// # Example
+///
+/// ```
+/// use carapax::{
+///     methods::AnswerCallbackQuery, handler, AnswerableCallbackQuery, Api, AutoAnswerCallbackQuery, Dispatcher,
+///     ExecuteError,
+/// };
+///
+/// #[handler]
+/// async fn handle_query(api: &Api, query: AnswerableCallbackQuery) -> Result<(), ExecuteError> {
+///     if query.data.as_deref() == Some("alert") {
+///         // auto-answer will not be sent
+///         query.answer(api, AnswerCallbackQuery::new(query.id.clone()).text("Alert!")).await?;
+///     }
+///     Ok(())
+/// }
+///
+/// let api = Api::new("token").unwrap();
+/// let mut dispatcher = Dispatcher::new(api.clone());
+/// dispatcher.add_handler(AutoAnswerCallbackQuery::new(api, handle_query));
+/// ```
+pub struct AutoAnswerCallbackQuery<H> {
+    api: Api,
+    handler: H,
+}
+
+impl<H> AutoAnswerCallbackQuery<H> {
+    /// Creates a new handler
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client used to send an answer
+    /// * handler - A handler to wrap
+    pub fn new(api: Api, handler: H) -> Self {
+        Self { api, handler }
+    }
+}
+
+#[async_trait]
+impl<C, H> Handler<C> for AutoAnswerCallbackQuery<H>
+where
+    C: Send + Sync,
+    H: Handler<C, Input = AnswerableCallbackQuery> + Send,
+{
+    type Input = Update;
+    type Output = HandlerResult;
+
+    async fn handle(&mut self, context: &C, input: Self::Input) -> Self::Output {
+        let query = match AnswerableCallbackQuery::try_from_update(input) {
+            Ok(Some(query)) => query,
+            _ => return HandlerResult::Continue,
+        };
+        let query_id = query.id.clone();
+        let answered = query.answered.clone();
+        let result = self.handler.handle(context, query).await.into();
+        if !answered.load(Ordering::SeqCst) {
+            if let Err(err) = self.api.execute(AnswerCallbackQuery::new(query_id)).await {
+                return match result {
+                    HandlerResult::Error(err) => HandlerResult::Error(err),
+                    _ => HandlerResult::error(err),
+                };
+            }
+        }
+        result
+    }
+}
+
+/// A callback query which keeps track of whether it was answered
+///
+/// Dereferences to [CallbackQuery](types/struct.CallbackQuery.html)
+#[derive(Clone, Debug)]
+pub struct AnswerableCallbackQuery {
+    query: CallbackQuery,
+    answered: Arc<AtomicBool>,
+}
+
+impl AnswerableCallbackQuery {
+    /// Sends an answer and marks the query as answered
+    pub async fn answer(&self, api: &Api, method: AnswerCallbackQuery) -> Result<bool, ExecuteError> {
+        let result = api.execute(method).await?;
+        self.set_answered();
+        Ok(result)
+    }
+
+    /// Marks the query as answered
+    ///
+    /// Use it when an answer was sent without [answer()](#method.answer)
+    pub fn set_answered(&self) {
+        self.answered.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the query was answered
+    pub fn is_answered(&self) -> bool {
+        self.answered.load(Ordering::SeqCst)
+    }
+
+    /// Returns the underlying query
+    pub fn into_inner(self) -> CallbackQuery {
+        self.query
+    }
+}
+
+impl Deref for AnswerableCallbackQuery {
+    type Target = CallbackQuery;
+
+    fn deref(&self) -> &Self::Target {
+        &self.query
+    }
+}
+
+impl TryFromUpdate for AnswerableCallbackQuery {
+    type Error = Infallible;
+
+    fn try_from_update(update: Update) -> Result<Option<Self>, Self::Error> {
+        Ok(match update.kind {
+            UpdateKind::CallbackQuery(query) => Some(Self {
+                query,
+                answered: Arc::new(AtomicBool::new(false)),
+            }),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ok_response, MockServer};
+
+    struct HandlerMock {
+        answer: bool,
+    }
+
+    #[async_trait]
+    impl Handler<()> for HandlerMock {
+        type Input = AnswerableCallbackQuery;
+        type Output = HandlerResult;
+
+        async fn handle(&mut self, _context: &(), input: Self::Input) -> Self::Output {
+            assert!(!input.is_answered());
+            if self.answer {
+                input.set_answered();
+            }
+            HandlerResult::Stop
+        }
+    }
+
+    fn create_update() -> Update {
+        serde_json::from_value(serde_json::json!({
+            "update_id": 1,
+            "callback_query": {
+                "id": "query-id",
+                "from": {"id": 1, "is_bot": false, "first_name": "test"},
+                "data": "data"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn answered() {
+        let server = MockServer::with_error();
+        let mut handler = AutoAnswerCallbackQuery::new(server.api(), HandlerMock { answer: true });
+        match handler.handle(&(), create_update()).await {
+            HandlerResult::Stop => {}
+            result => panic!("Unexpected handler result: {:?}", result),
+        }
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn not_answered() {
+        let server = MockServer::new(|_, _| ok_response(serde_json::json!(true)));
+        let mut handler = AutoAnswerCallbackQuery::new(server.api(), HandlerMock { answer: false });
+        match handler.handle(&(), create_update()).await {
+            HandlerResult::Stop => {}
+            result => panic!("Unexpected handler result: {:?}", result),
+        }
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "answerCallbackQuery");
+        assert_eq!(requests[0].body, serde_json::json!({"callback_query_id": "query-id"}));
+    }
+
+    #[tokio::test]
+    async fn answer_failed() {
+        let server = MockServer::with_error();
+        let mut handler = AutoAnswerCallbackQuery::new(server.api(), HandlerMock { answer: false });
+        match handler.handle(&(), create_update()).await {
+            HandlerResult::Error(_) => {}
+            result => panic!("Unexpected handler result: {:?}", result),
+        }
+        assert_eq!(server.methods(), vec!["answerCallbackQuery"]);
+    }
+
+    #[tokio::test]
+    async fn skip_other_updates() {
+        let update: Update = serde_json::from_value(serde_json::json!({
+            "update_id": 1,
+            "message": {
+                "message_id": 1111,
+                "date": 0,
+                "from": {"id": 1, "is_bot": false, "first_name": "test"},
+                "chat": {"id": 1, "type": "private", "first_name": "test"},
+                "text": "test"
+            }
+        }))
+        .unwrap();
+        let server = MockServer::with_error();
+        let mut handler = AutoAnswerCallbackQuery::new(server.api(), HandlerMock { answer: false });
+        match handler.handle(&(), update).await {
+            HandlerResult::Continue => {}
+            result => panic!("Unexpected handler result: {:?}", result),
+        }
+        assert!(server.requests().is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+use tgbot::types::{Chat, Message};
+
+/// Additional accessors for [Chat](types/enum.Chat.html)
+///
+/// # Example
+///
+/// ```
+/// use carapax::{methods::GetChat, types::Integer, Api, ChatExt, ExecuteError};
+///
+/// async fn get_pinned_text(api: &Api, chat_id: Integer) -> Result<Option<String>, ExecuteError> {
+///     let chat = api.execute(GetChat::new(chat_id)).await?;
+///     Ok(chat
+///         .pinned_message()
+///         .and_then(|message| message.get_text())
+///         .map(|text| text.data.clone()))
+/// }
+/// ```
+pub trait ChatExt {
+    /// Returns a pinned message of a group, supergroup or channel
+    ///
+    /// Private chats have no pinned message.
+    /// Note that the message is returned only by `getChat` method.
+    fn pinned_message(&self) -> Option<&Message>;
+}
+
+impl ChatExt for Chat {
+    fn pinned_message(&self) -> Option<&Message> {
+        let message = match self {
+            Chat::Channel(chat) => chat.pinned_message.as_ref(),
+            Chat::Group(chat) => chat.pinned_message.as_ref(),
+            Chat::Private(_) => None,
+            Chat::Supergroup(chat) => chat.pinned_message.as_ref(),
+        };
+        message.map(|message| message.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn create_chat(mut chat: Value, pinned_text: Option<&str>) -> Chat {
+        if let Some(text) = pinned_text {
+            let mut message = json!({
+                "message_id": 1,
+                "date": 0,
+                "chat": chat.clone(),
+                "text": text
+            });
+            // messages in channels have no sender
+            if chat["type"] != "channel" {
+                message["from"] = json!({"id": 1, "is_bot": false, "first_name": "test"});
+            }
+            chat["pinned_message"] = message;
+        }
+        serde_json::from_value(chat).unwrap()
+    }
+
+    fn get_pinned_text(chat: &Chat) -> Option<&str> {
+        chat.pinned_message()
+            .and_then(|message| message.get_text())
+            .map(|text| text.data.as_str())
+    }
+
+    #[test]
+    fn pinned_message() {
+        for chat in [
+            json!({"id": -1, "type": "channel", "title": "channel"}),
+            json!({"id": -2, "type": "group", "title": "group", "all_members_are_administrators": true}),
+            json!({"id": -3, "type": "supergroup", "title": "supergroup"}),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                get_pinned_text(&create_chat(chat.clone(), Some("pinned"))),
+                Some("pinned")
+            );
+            assert!(create_chat(chat.clone(), None).pinned_message().is_none());
+        }
+        let chat = create_chat(json!({"id": 1, "type": "private", "first_name": "test"}), None);
+        assert!(chat.pinned_message().is_none());
+    }
+}
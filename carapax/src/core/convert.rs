@@ -171,7 +171,7 @@ mod tests {
 
     #[test]
     fn message() {
-        for data in vec![
+        for data in [
             serde_json::json!({
                 "update_id": 1,
                 "message": {
@@ -213,8 +213,10 @@ mod tests {
                     "edit_date": 1213
                 }
             }),
-        ] {
-            let update: Update = serde_json::from_value(data).unwrap();
+        ]
+        .iter()
+        {
+            let update: Update = serde_json::from_value(data.clone()).unwrap();
             assert!(Update::try_from_update(update.clone()).unwrap().is_some());
             assert!(Message::try_from_update(update).unwrap().is_some());
         }
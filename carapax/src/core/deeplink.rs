@@ -0,0 +1,141 @@
+use std::{error::Error, fmt};
+use tgbot::types::Command;
+
+/// Maximum length of a start parameter allowed by Telegram
+pub const MAX_START_PARAM_LEN: usize = 64;
+
+const START_COMMAND: &str = "/start";
+
+/// Encodes data into a start parameter of a deep link
+///
+/// Data is encoded using URL-safe base64 without padding,
+/// so only characters allowed by Telegram are used.
+/// Up to 48 bytes fit into a parameter.
+///
+/// # Arguments
+///
+/// * data - Data to encode
+pub fn encode_start_param(data: &[u8]) -> Result<String, StartParamError> {
+    let param = base64::encode_config(data, base64::URL_SAFE_NO_PAD);
+    if param.len() > MAX_START_PARAM_LEN {
+        Err(StartParamError::TooLong(param.len()))
+    } else {
+        Ok(param)
+    }
+}
+
+/// Decodes data from a start parameter created by [encode_start_param()](fn.encode_start_param.html)
+///
+/// # Arguments
+///
+/// * param - A start parameter
+pub fn decode_start_param(param: &str) -> Result<Vec<u8>, StartParamError> {
+    if param.len() > MAX_START_PARAM_LEN {
+        return Err(StartParamError::TooLong(param.len()));
+    }
+    base64::decode_config(param, base64::URL_SAFE_NO_PAD).map_err(StartParamError::Decode)
+}
+
+/// Returns a deep link which starts a bot with given parameter
+///
+/// # Arguments
+///
+/// * bot_username - Username of a bot, leading `@` is optional
+/// * param - A start parameter
+pub fn start_link(bot_username: &str, param: &str) -> String {
+    format!("https://t.me/{}?start={}", bot_username.trim_start_matches('@'), param)
+}
+
+/// Returns a start parameter passed to a `/start` command using a deep link
+///
+/// # Example
+///
+/// ```
+/// use carapax::{decode_start_param, get_start_param, handler, types::Command};
+///
+/// #[handler(command = "/start")]
+/// async fn handle_start(_context: &(), command: Command) {
+///     if let Some(referrer) = get_start_param(&command).and_then(|param| decode_start_param(param).ok()) {
+///         println!("Referred by: {:?}", referrer);
+///     }
+/// }
+/// ```
+pub fn get_start_param(command: &Command) -> Option<&str> {
+    if command.get_name() == START_COMMAND {
+        command.get_args().first().map(String::as_str)
+    } else {
+        None
+    }
+}
+
+/// An error when encoding or decoding a start parameter
+#[derive(Debug)]
+pub enum StartParamError {
+    /// Failed to decode a parameter
+    Decode(base64::DecodeError),
+    /// Parameter is longer than 64 characters
+    TooLong(usize),
+}
+
+impl Error for StartParamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StartParamError::Decode(err) => Some(err),
+            StartParamError::TooLong(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for StartParamError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StartParamError::Decode(err) => write!(out, "failed to decode start parameter: {}", err),
+            StartParamError::TooLong(len) => write!(
+                out,
+                "start parameter has {} characters, max is {}",
+                len, MAX_START_PARAM_LEN
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+    use tgbot::types::Message;
+
+    fn create_command(text: &str) -> Command {
+        let message: Message = serde_json::from_value(serde_json::json!({
+            "message_id": 1111,
+            "date": 0,
+            "from": {"id": 1, "is_bot": false, "first_name": "test"},
+            "chat": {"id": 1, "type": "private", "first_name": "test"},
+            "text": text,
+            "entities": [{"type": "bot_command", "offset": 0, "length": text.find(' ').unwrap_or(text.len())}]
+        }))
+        .unwrap();
+        Command::try_from(message).unwrap()
+    }
+
+    #[test]
+    fn start_param() {
+        let data = [0xfb, 0xff, 0x00, 0x01];
+        let param = encode_start_param(&data).unwrap();
+        assert_eq!(param, "-_8AAQ");
+        assert_eq!(decode_start_param(&param).unwrap(), data);
+
+        assert_eq!(encode_start_param(&[0; 48]).unwrap().len(), MAX_START_PARAM_LEN);
+        assert!(matches!(
+            encode_start_param(&[0; 49]),
+            Err(StartParamError::TooLong(66))
+        ));
+        assert!(matches!(decode_start_param("a+b"), Err(StartParamError::Decode(_))));
+
+        assert_eq!(start_link("@testbot", &param), "https://t.me/testbot?start=-_8AAQ");
+
+        assert_eq!(get_start_param(&create_command("/start -_8AAQ")), Some("-_8AAQ"));
+        assert_eq!(get_start_param(&create_command("/start")), None);
+        assert_eq!(get_start_param(&create_command("/help -_8AAQ")), None);
+    }
+}
@@ -4,8 +4,12 @@ use crate::core::{
     result::{HandlerError, HandlerResult},
 };
 use async_trait::async_trait;
-use std::sync::Arc;
-use tgbot::{types::Update, UpdateHandler};
+use futures_util::future::FutureExt;
+use std::{any::Any, error::Error, fmt, panic::AssertUnwindSafe, sync::Arc};
+use tgbot::{
+    types::{Integer, Update},
+    UpdateHandler,
+};
 
 type BoxedHandler<C> = Box<dyn Handler<C, Input = Update, Output = HandlerResult> + Send>;
 type BoxedErrorHandler = Box<dyn ErrorHandler + Send>;
@@ -15,6 +19,7 @@ pub struct Dispatcher<C> {
     handlers: Vec<BoxedHandler<C>>,
     context: Arc<C>,
     error_handler: BoxedErrorHandler,
+    catch_panics: bool,
 }
 
 impl<C> Dispatcher<C>
@@ -31,6 +36,7 @@ where
             context: Arc::new(context),
             handlers: Vec::new(),
             error_handler: Box::new(LoggingErrorHandler::default()),
+            catch_panics: false,
         }
     }
 
@@ -56,10 +62,32 @@ where
         self.error_handler = Box::new(handler);
     }
 
+    /// Sets whether a panic in a handler should be caught
+    ///
+    /// When enabled, a panic is converted into [HandlerPanic](struct.HandlerPanic.html)
+    /// and passed to error handler, so one bad update does not stop polling.
+    /// Handlers are not required to be `UnwindSafe`,
+    /// so make sure that a panicked handler does not leave a shared state broken.
+    ///
+    /// Disabled by default.
+    pub fn set_catch_panics(&mut self, value: bool) {
+        self.catch_panics = value;
+    }
+
     pub(crate) async fn dispatch(&mut self, update: Update) {
         let context = self.context.clone();
         for handler in &mut self.handlers {
-            let result = handler.handle(&context, update.clone()).await;
+            let result = if self.catch_panics {
+                match AssertUnwindSafe(handler.handle(&context, update.clone()))
+                    .catch_unwind()
+                    .await
+                {
+                    Ok(result) => result,
+                    Err(payload) => HandlerResult::error(HandlerPanic::new(update.id, payload)),
+                }
+            } else {
+                handler.handle(&context, update.clone()).await
+            };
             match result {
                 HandlerResult::Continue => continue,
                 HandlerResult::Stop => break,
@@ -82,6 +110,48 @@ where
     }
 }
 
+/// A panic occurred in a handler
+///
+/// See [Dispatcher::set_catch_panics()](struct.Dispatcher.html#method.set_catch_panics)
+#[derive(Debug)]
+pub struct HandlerPanic {
+    update_id: Integer,
+    message: Option<String>,
+}
+
+impl HandlerPanic {
+    fn new(update_id: Integer, payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => Some(*message),
+            Err(payload) => payload.downcast_ref::<&str>().map(|message| String::from(*message)),
+        };
+        Self { update_id, message }
+    }
+
+    /// ID of the update being processed
+    pub fn update_id(&self) -> Integer {
+        self.update_id
+    }
+
+    /// Panic message
+    ///
+    /// None if panic was called with a non-string payload
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+impl Error for HandlerPanic {}
+
+impl fmt::Display for HandlerPanic {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self.message {
+            Some(ref message) => write!(out, "handler panicked on update {}: {}", self.update_id, message),
+            None => write!(out, "handler panicked on update {}", self.update_id),
+        }
+    }
+}
+
 /// A handler for errors occurred when dispatching update
 #[async_trait]
 pub trait ErrorHandler {
@@ -136,7 +206,6 @@ pub enum ErrorPolicy {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{error::Error, fmt};
     use tokio::sync::{
         oneshot::{channel, Sender},
         Mutex,
@@ -164,6 +233,10 @@ mod tests {
         fn with_error() -> Self {
             Self::new(HandlerResult::from(Err::<(), ErrorMock>(ErrorMock)))
         }
+
+        fn with_panic() -> Self {
+            Self { result: None }
+        }
     }
 
     #[async_trait]
@@ -173,7 +246,7 @@ mod tests {
 
         async fn handle(&mut self, context: &Updates, input: Self::Input) -> Self::Output {
             context.lock().await.push(input);
-            self.result.take().unwrap()
+            self.result.take().expect("handler panicked")
         }
     }
 
@@ -277,4 +350,23 @@ mod tests {
             assert!(rx.try_recv().is_ok());
         }
     }
+
+    #[tokio::test]
+    async fn dispatch_catch_panics() {
+        let update = create_update();
+        for (count, error_policy) in &[(1usize, ErrorPolicy::Stop), (2usize, ErrorPolicy::Continue)] {
+            let mut dispatcher = Dispatcher::new(Mutex::new(Vec::new()));
+            dispatcher.set_catch_panics(true);
+            dispatcher.add_handler(HandlerMock::with_panic());
+            dispatcher.add_handler(HandlerMock::with_continue());
+            let (tx, mut rx) = channel();
+            dispatcher.set_error_handler(MockErrorHandler::new(*error_policy, tx));
+            dispatcher.dispatch(update.clone()).await;
+            rx.close();
+            let context = dispatcher.context.lock().await;
+            assert_eq!(context.len(), *count);
+            let err = rx.try_recv().unwrap();
+            assert_eq!(err.to_string(), "handler panicked on update 1: handler panicked");
+        }
+    }
 }
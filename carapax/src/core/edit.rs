@@ -0,0 +1,423 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tgbot::{
+    methods::EditMessageText,
+    types::{ChatId, Integer},
+    Api, ExecuteError,
+};
+use tokio::runtime::Handle;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(1000);
+const DEFAULT_CAPACITY: usize = 1000;
+
+type MessageKey = (Integer, Integer);
+
+/// Edits text of a message not more often than given interval
+///
+/// Useful for progress messages which are updated frequently:
+/// intermediate values are coalesced, so only the latest one is sent.
+/// The last value is sent on [flush()](#method.flush) or when the value is dropped.
+/// Sending on drop requires a running tokio runtime and errors are only logged,
+/// so prefer calling `flush()` explicitly.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{types::Message, Api, ExecuteError, ThrottledEdit};
+///
+/// async fn run_task(api: &Api, message: Message) -> Result<(), ExecuteError> {
+///     let mut progress = ThrottledEdit::new(api.clone(), message.get_chat_id(), message.id);
+///     for idx in 1..=100 {
+///         // do some work here
+///         progress.update(format!("{}%", idx)).await?;
+///     }
+///     progress.flush().await
+/// }
+/// ```
+pub struct ThrottledEdit {
+    api: Api,
+    chat_id: ChatId,
+    message_id: Integer,
+    interval: Duration,
+    sent_at: Option<Instant>,
+    sent_text: Option<String>,
+    pending_text: Option<String>,
+}
+
+impl ThrottledEdit {
+    /// Creates a new ThrottledEdit
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client used to send requests
+    /// * chat_id - ID of a chat
+    /// * message_id - ID of a message to edit
+    pub fn new<C>(api: Api, chat_id: C, message_id: Integer) -> Self
+    where
+        C: Into<ChatId>,
+    {
+        Self {
+            api,
+            chat_id: chat_id.into(),
+            message_id,
+            interval: DEFAULT_INTERVAL,
+            sent_at: None,
+            sent_text: None,
+            pending_text: None,
+        }
+    }
+
+    /// Sets minimal interval between requests
+    ///
+    /// Default value is 1 second
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets a new text
+    ///
+    /// Text is sent immediately if the interval has passed since last request,
+    /// otherwise it is kept until next call of `update()` or `flush()`
+    pub async fn update<T>(&mut self, text: T) -> Result<(), ExecuteError>
+    where
+        T: Into<String>,
+    {
+        self.pending_text = Some(text.into());
+        match self.sent_at {
+            Some(sent_at) if sent_at.elapsed() < self.interval => Ok(()),
+            _ => self.flush().await,
+        }
+    }
+
+    /// Sends a pending text if any
+    ///
+    /// Text is kept as pending when request has failed
+    pub async fn flush(&mut self) -> Result<(), ExecuteError> {
+        if let Some(text) = self.take_pending() {
+            match self.api.execute(self.create_method(text.clone())).await {
+                Ok(_) => {}
+                Err(ExecuteError::Response(ref err)) if is_not_modified(err.description()) => {}
+                Err(err) => {
+                    self.pending_text = Some(text);
+                    return Err(err);
+                }
+            }
+            self.sent_at = Some(Instant::now());
+            self.sent_text = Some(text);
+        }
+        Ok(())
+    }
+
+    fn take_pending(&mut self) -> Option<String> {
+        let text = self.pending_text.take()?;
+        // Telegram returns an error when text is not modified
+        if self.sent_text.as_ref() == Some(&text) {
+            None
+        } else {
+            Some(text)
+        }
+    }
+
+    fn create_method(&self, text: String) -> EditMessageText {
+        EditMessageText::new(self.chat_id.clone(), self.message_id, text)
+    }
+}
+
+impl Drop for ThrottledEdit {
+    fn drop(&mut self) {
+        if let Some(text) = self.take_pending() {
+            match Handle::try_current() {
+                Ok(handle) => {
+                    let api = self.api.clone();
+                    let method = self.create_method(text);
+                    handle.spawn(async move {
+                        if let Err(err) = api.execute(method).await {
+                            log::error!("Failed to edit message: {}", err);
+                        }
+                    });
+                }
+                Err(_) => log::error!("Failed to edit message: no tokio runtime"),
+            }
+        }
+    }
+}
+
+/// Edits text of messages skipping requests which do not change it
+///
+/// Remembers the last text set for each message,
+/// so an edit with the same text does not send a request.
+/// "Message is not modified" error is considered as success.
+/// Cache is shared between clones.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{types::Message, Api, EditTextCache, ExecuteError};
+///
+/// async fn report_status(cache: &EditTextCache, message: &Message, status: &str) -> Result<(), ExecuteError> {
+///     cache
+///         .edit_text_if_changed(message.get_chat_id(), message.id, status)
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct EditTextCache {
+    api: Api,
+    capacity: usize,
+    texts: Arc<Mutex<EditedTexts>>,
+}
+
+impl EditTextCache {
+    /// Creates a new cache
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client used to send requests
+    pub fn new(api: Api) -> Self {
+        Self {
+            api,
+            capacity: DEFAULT_CAPACITY,
+            texts: Arc::new(Mutex::new(EditedTexts::default())),
+        }
+    }
+
+    /// Sets max number of remembered messages
+    ///
+    /// Least recently edited message is forgotten when capacity is exceeded.
+    /// Default value is 1000
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Edits text of a message if it differs from the last text set
+    ///
+    /// Returns `true` if text was changed.
+    ///
+    /// # Arguments
+    ///
+    /// * chat_id - ID of a chat
+    /// * message_id - ID of a message to edit
+    /// * text - New text
+    pub async fn edit_text_if_changed<T>(
+        &self,
+        chat_id: Integer,
+        message_id: Integer,
+        text: T,
+    ) -> Result<bool, ExecuteError>
+    where
+        T: Into<String>,
+    {
+        let text = text.into();
+        let key = (chat_id, message_id);
+        // text is reserved before sending, so a concurrent edit with the same text is skipped
+        let previous = match self.reserve(key, &text) {
+            Some(previous) => previous,
+            None => return Ok(false),
+        };
+        match self
+            .api
+            .execute(EditMessageText::new(chat_id, message_id, text.clone()))
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(ExecuteError::Response(ref err)) if is_not_modified(err.description()) => Ok(false),
+            Err(err) => {
+                self.restore(key, &text, previous);
+                Err(err)
+            }
+        }
+    }
+
+    /// Sets a new text for a message
+    ///
+    /// Returns None if the text is already set, otherwise a previous text
+    fn reserve(&self, key: MessageKey, text: &str) -> Option<Option<String>> {
+        let mut texts = self.texts.lock().expect("Edit text cache lock poisoned");
+        if texts.get(key) == Some(text) {
+            return None;
+        }
+        Some(texts.insert(key, String::from(text), self.capacity))
+    }
+
+    /// Restores a previous text of a message when edit has failed
+    fn restore(&self, key: MessageKey, text: &str, previous: Option<String>) {
+        let mut texts = self.texts.lock().expect("Edit text cache lock poisoned");
+        // text could be changed by another edit
+        if texts.get(key) == Some(text) {
+            match previous {
+                Some(previous) => {
+                    texts.insert(key, previous, self.capacity);
+                }
+                None => {
+                    texts.remove(key);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct EditedTexts {
+    counter: u64,
+    // message => (text, usage counter)
+    texts: HashMap<MessageKey, (String, u64)>,
+    // usage counter => message
+    usage: BTreeMap<u64, MessageKey>,
+}
+
+impl EditedTexts {
+    fn get(&self, key: MessageKey) -> Option<&str> {
+        self.texts.get(&key).map(|(text, _)| text.as_str())
+    }
+
+    fn insert(&mut self, key: MessageKey, text: String, capacity: usize) -> Option<String> {
+        let previous = self.remove(key);
+        if capacity == 0 {
+            return previous;
+        }
+        self.counter += 1;
+        self.texts.insert(key, (text, self.counter));
+        self.usage.insert(self.counter, key);
+        while self.texts.len() > capacity {
+            // least recently edited message is forgotten
+            let oldest = match self.usage.values().next() {
+                Some(oldest) => *oldest,
+                None => break,
+            };
+            self.remove(oldest);
+        }
+        previous
+    }
+
+    fn remove(&mut self, key: MessageKey) -> Option<String> {
+        let (text, used_at) = self.texts.remove(&key)?;
+        self.usage.remove(&used_at);
+        Some(text)
+    }
+}
+
+fn is_not_modified(description: &str) -> bool {
+    description.to_lowercase().contains("message is not modified")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{error_response, ok_response, MockServer};
+    use serde_json::{json, Value};
+
+    fn create_server() -> MockServer {
+        MockServer::new(|_, body| match body["text"].as_str() {
+            Some("fail") => error_response(),
+            Some("same") => json!({
+                "ok": false,
+                "error_code": 400,
+                "description": "Bad Request: message is not modified"
+            }),
+            _ => ok_response(json!(true)),
+        })
+    }
+
+    fn get_sent(server: &MockServer) -> Vec<(Value, String)> {
+        server
+            .requests()
+            .into_iter()
+            .map(|request| {
+                assert_eq!(request.method, "editMessageText");
+                assert_eq!(request.body["chat_id"], 1);
+                (
+                    request.body["message_id"].clone(),
+                    request.body["text"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn throttle() {
+        let server = create_server();
+        let mut edit = ThrottledEdit::new(server.api(), 1, 1).interval(Duration::from_secs(60));
+        // first update is sent immediately and kept as pending on error
+        assert!(edit.update("fail").await.is_err());
+        assert_eq!(edit.pending_text.as_deref(), Some("fail"));
+        edit.update("1%").await.unwrap();
+        assert!(edit.pending_text.is_none());
+
+        // interval has not passed
+        edit.update("2%").await.unwrap();
+        edit.update("3%").await.unwrap();
+        assert_eq!(edit.pending_text.as_deref(), Some("3%"));
+        edit.flush().await.unwrap();
+
+        // text is sent already
+        edit.update("3%").await.unwrap();
+        edit.flush().await.unwrap();
+
+        // text is not modified
+        edit.update("same").await.unwrap();
+        edit.flush().await.unwrap();
+        assert!(edit.pending_text.is_none());
+
+        assert_eq!(
+            get_sent(&server),
+            vec![
+                (json!(1), String::from("fail")),
+                (json!(1), String::from("1%")),
+                (json!(1), String::from("3%")),
+                (json!(1), String::from("same")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn edit_text_if_changed() {
+        let server = create_server();
+        let cache = EditTextCache::new(server.api()).capacity(1);
+        assert!(cache.edit_text_if_changed(1, 1, "fail").await.is_err());
+        assert!(cache.edit_text_if_changed(1, 1, "text").await.unwrap());
+        assert!(!cache.edit_text_if_changed(1, 1, "text").await.unwrap());
+
+        // previous text is restored when request has failed
+        assert!(cache.edit_text_if_changed(1, 1, "fail").await.is_err());
+        assert!(!cache.edit_text_if_changed(1, 1, "text").await.unwrap());
+
+        // text is not modified
+        assert!(!cache.edit_text_if_changed(1, 1, "same").await.unwrap());
+        assert!(!cache.edit_text_if_changed(1, 1, "same").await.unwrap());
+
+        // capacity exceeded, so first message is forgotten
+        assert!(cache.edit_text_if_changed(1, 2, "text").await.unwrap());
+        assert!(!cache.edit_text_if_changed(1, 1, "same").await.unwrap());
+
+        // concurrent edits with the same text send a single request
+        let (first, second) = tokio::join!(
+            cache.edit_text_if_changed(1, 3, "text"),
+            cache.edit_text_if_changed(1, 3, "text")
+        );
+        assert_ne!(first.unwrap(), second.unwrap());
+
+        assert_eq!(
+            get_sent(&server),
+            vec![
+                (json!(1), String::from("fail")),
+                (json!(1), String::from("text")),
+                (json!(1), String::from("fail")),
+                (json!(1), String::from("same")),
+                (json!(2), String::from("text")),
+                (json!(1), String::from("same")),
+                (json!(3), String::from("text")),
+            ]
+        );
+
+        assert!(is_not_modified(
+            "Bad Request: message is not modified: specified new message content \
+             and reply markup are exactly the same as a current content and reply markup of the message"
+        ));
+    }
+}
@@ -0,0 +1,104 @@
+use tgbot::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+/// Additional constructors for [InlineKeyboardMarkup](types/struct.InlineKeyboardMarkup.html)
+///
+/// # Example
+///
+/// ```
+/// use carapax::{types::{InlineKeyboardButton, InlineKeyboardMarkup}, InlineKeyboardMarkupExt};
+///
+/// // a numeric keypad: 1-9 and 0 in the middle of the last row
+/// let keypad = InlineKeyboardMarkup::from_grid(4, 3, |row, col| {
+///     let digit = match (row, col) {
+///         (3, 1) => 0,
+///         (3, _) => return None,
+///         _ => row * 3 + col + 1,
+///     };
+///     Some(InlineKeyboardButton::with_callback_data(digit.to_string(), format!("digit:{}", digit)))
+/// });
+/// ```
+pub trait InlineKeyboardMarkupExt {
+    /// Creates a markup from a grid of buttons
+    ///
+    /// Cells for which the closure returns None are skipped,
+    /// so rows may have different length; rows without buttons are skipped too.
+    ///
+    /// # Arguments
+    ///
+    /// * rows - Number of rows
+    /// * cols - Number of columns
+    /// * f - Returns a button for a row and a column
+    fn from_grid<F>(rows: usize, cols: usize, f: F) -> Self
+    where
+        F: FnMut(usize, usize) -> Option<InlineKeyboardButton>;
+}
+
+impl InlineKeyboardMarkupExt for InlineKeyboardMarkup {
+    fn from_grid<F>(rows: usize, cols: usize, mut f: F) -> Self
+    where
+        F: FnMut(usize, usize) -> Option<InlineKeyboardButton>,
+    {
+        let mut markup = Vec::with_capacity(rows);
+        for row in 0..rows {
+            let buttons: Vec<InlineKeyboardButton> = (0..cols).filter_map(|col| f(row, col)).collect();
+            if !buttons.is_empty() {
+                markup.push(buttons);
+            }
+        }
+        InlineKeyboardMarkup::from_vec(markup)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_texts(markup: InlineKeyboardMarkup) -> Vec<Vec<String>> {
+        markup
+            .into_vec()
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|button| {
+                        serde_json::to_value(button).unwrap()["text"]
+                            .as_str()
+                            .unwrap()
+                            .to_string()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keypad() {
+        let markup = InlineKeyboardMarkup::from_grid(3, 3, |row, col| {
+            let digit = (row * 3 + col + 1).to_string();
+            Some(InlineKeyboardButton::with_callback_data(digit.clone(), digit))
+        });
+        assert_eq!(
+            get_texts(markup),
+            vec![vec!["1", "2", "3"], vec!["4", "5", "6"], vec!["7", "8", "9"]]
+        );
+    }
+
+    #[test]
+    fn ragged_rows() {
+        // a triangle with an empty row in the middle
+        let markup = InlineKeyboardMarkup::from_grid(4, 3, |row, col| {
+            if row == 1 || col > row {
+                None
+            } else {
+                Some(InlineKeyboardButton::with_callback_data(
+                    format!("{}{}", row, col),
+                    "data",
+                ))
+            }
+        });
+        assert_eq!(
+            get_texts(markup),
+            vec![vec!["00"], vec!["20", "21", "22"], vec!["30", "31", "32"]]
+        );
+        assert!(InlineKeyboardMarkup::from_grid(0, 3, |_, _| None).into_vec().is_empty());
+    }
+}
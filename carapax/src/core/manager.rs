@@ -0,0 +1,217 @@
+use futures_util::future::{join_all, select, BoxFuture, Either, FutureExt};
+use std::sync::Arc;
+use tgbot::{
+    longpoll::{LongPoll, LongPollHandle, LongPollOptions},
+    Api, UpdateHandler,
+};
+use tokio::sync::watch;
+
+/// Runs long polling for several bots in one task
+///
+/// Each bot has its own API client, handler and long polling options.
+///
+/// Note that bots do not share an HTTP connection pool:
+/// tgbot `Api::new()` always builds a new HTTP client and can not accept an existing one.
+/// Clones of an `Api` share its client, so pass clones of the same `Api`
+/// to the manager and to handlers of a bot to keep one pool per bot.
+///
+/// # Example
+///
+/// ```no_run
+/// use carapax::{Api, BotManager, Dispatcher};
+/// use std::time::Duration;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let first = Api::new("first-token").unwrap();
+///     let second = Api::new("second-token").unwrap();
+///     let mut manager = BotManager::new();
+///     manager.add(first.clone(), Dispatcher::new(first));
+///     manager.add(second.clone(), Dispatcher::new(second));
+///     let handle = manager.get_handle();
+///     tokio::spawn(async move {
+///         tokio::time::delay_for(Duration::from_secs(3600)).await;
+///         handle.shutdown();
+///     });
+///     manager.run_all().await;
+/// }
+/// ```
+pub struct BotManager {
+    bots: Vec<Bot>,
+    sender: Arc<watch::Sender<bool>>,
+    receiver: watch::Receiver<bool>,
+}
+
+struct Bot {
+    poll: BoxFuture<'static, ()>,
+    handle: LongPollHandle,
+}
+
+impl BotManager {
+    /// Creates a new manager without bots
+    pub fn new() -> Self {
+        let (sender, receiver) = watch::channel(false);
+        Self {
+            bots: Vec::new(),
+            sender: Arc::new(sender),
+            receiver,
+        }
+    }
+
+    /// Adds a bot with default long polling options
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client of the bot
+    /// * handler - Handler of the bot updates
+    pub fn add<H>(&mut self, api: Api, handler: H) -> &mut Self
+    where
+        H: UpdateHandler + Send + 'static,
+    {
+        self.add_with_options(api, handler, LongPollOptions::default())
+    }
+
+    /// Adds a bot with given long polling options
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client of the bot
+    /// * handler - Handler of the bot updates
+    /// * options - Long polling options of the bot
+    pub fn add_with_options<H>(&mut self, api: Api, handler: H, options: LongPollOptions) -> &mut Self
+    where
+        H: UpdateHandler + Send + 'static,
+    {
+        let poll = LongPoll::new(api, handler).options(options);
+        let handle = poll.get_handle();
+        self.bots.push(Bot {
+            poll: poll.run().boxed(),
+            handle,
+        });
+        self
+    }
+
+    /// Returns a handle to shutdown all bots
+    pub fn get_handle(&self) -> BotManagerHandle {
+        BotManagerHandle {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Runs long polling for all bots
+    ///
+    /// Returns when all bots are stopped.
+    pub async fn run_all(self) {
+        // bots stop when the sender is dropped, so keep it until all bots are stopped
+        let BotManager {
+            bots,
+            sender: _sender,
+            receiver,
+        } = self;
+        join_all(bots.into_iter().map(|bot| run_bot(bot, receiver.clone()))).await;
+    }
+}
+
+impl Default for BotManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn run_bot(bot: Bot, mut receiver: watch::Receiver<bool>) {
+    let Bot { poll, handle } = bot;
+    let shutdown = async move {
+        // the first received value is the current one
+        while let Some(value) = receiver.recv().await {
+            if value {
+                break;
+            }
+        }
+    }
+    .boxed();
+    if let Either::Right(((), poll)) = select(poll, shutdown).await {
+        handle.shutdown().await;
+        // long polling stops after the current getUpdates request
+        poll.await;
+    }
+}
+
+/// A handle to shutdown bots started by [BotManager](struct.BotManager.html)
+#[derive(Clone)]
+pub struct BotManagerHandle {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl BotManagerHandle {
+    /// Stops long polling of all bots
+    ///
+    /// Updates which are being processed are handled before bots stop.
+    pub fn shutdown(&self) {
+        let _ = self.sender.broadcast(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ok_response, MockServer};
+    use async_trait::async_trait;
+    use serde_json::json;
+    use tgbot::types::{Integer, Update};
+    use tokio::sync::mpsc;
+
+    struct Forward(mpsc::UnboundedSender<Integer>);
+
+    #[async_trait]
+    impl UpdateHandler for Forward {
+        async fn handle(&mut self, update: Update) {
+            self.0.send(update.id).unwrap();
+        }
+    }
+
+    fn create_server() -> MockServer {
+        MockServer::new(|_, body| {
+            if body["offset"] == 1 {
+                ok_response(json!([{
+                    "update_id": 1,
+                    "message": {
+                        "message_id": 1,
+                        "date": 0,
+                        "from": {"id": 1, "is_bot": false, "first_name": "test"},
+                        "chat": {"id": 1, "type": "private", "first_name": "test"},
+                        "text": "test"
+                    }
+                }]))
+            } else {
+                ok_response(json!([]))
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn shutdown() {
+        let servers = vec![create_server(), create_server()];
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let mut manager = BotManager::new();
+        for server in &servers {
+            manager.add_with_options(
+                server.api(),
+                Forward(sender.clone()),
+                LongPollOptions::default().error_timeout(0),
+            );
+        }
+        let handle = manager.get_handle();
+        tokio::join!(manager.run_all(), async {
+            // every bot has received an update
+            assert_eq!(receiver.recv().await, Some(1));
+            assert_eq!(receiver.recv().await, Some(1));
+            handle.shutdown();
+        });
+        for server in &servers {
+            let requests = server.requests();
+            assert!(requests.iter().all(|request| request.method == "getUpdates"));
+            assert_eq!(requests[0].body["offset"], 1);
+            assert_eq!(requests[1].body["offset"], 2);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use tgbot::{
+    methods::Method,
+    types::{Integer, Message},
+    Api, ExecuteError,
+};
+
+/// Sends a message and retries once when a group has migrated to a supergroup
+///
+/// When a group is upgraded to a supergroup, its ID changes and requests to the old ID fail
+/// with an error containing `migrate_to_chat_id` parameter.
+/// In this case a method is built again for the new ID and sent once more.
+/// Compare [get_chat_id()](types/struct.Message.html#method.get_chat_id)
+/// of a returned message with the old ID to find out whether a stored ID has to be updated.
+///
+/// # Arguments
+///
+/// * api - Client used to send requests
+/// * chat_id - ID of a chat
+/// * build - Returns a method which sends a message to given chat
+///
+/// # Example
+///
+/// ```
+/// use carapax::{execute_with_migration, methods::SendMessage, types::Integer, Api, ExecuteError};
+///
+/// async fn notify(api: &Api, chat_id: Integer) -> Result<Integer, ExecuteError> {
+///     let message = execute_with_migration(api, chat_id, |chat_id| SendMessage::new(chat_id, "Hello!")).await?;
+///     // store the new ID when the chat has migrated
+///     Ok(message.get_chat_id())
+/// }
+/// ```
+pub async fn execute_with_migration<F, M>(api: &Api, chat_id: Integer, mut build: F) -> Result<Message, ExecuteError>
+where
+    F: FnMut(Integer) -> M,
+    M: Method<Response = Message>,
+{
+    let new_chat_id = match api.execute(build(chat_id)).await {
+        Err(ExecuteError::Response(err)) => match err.migrate_to_chat_id() {
+            Some(new_chat_id) => new_chat_id,
+            None => return Err(ExecuteError::Response(err)),
+        },
+        result => return result,
+    };
+    log::info!("Chat {} has migrated to {}", chat_id, new_chat_id);
+    api.execute(build(new_chat_id)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{error_response, ok_response, MockServer};
+    use serde_json::json;
+    use tgbot::methods::SendMessage;
+
+    fn create_server() -> MockServer {
+        MockServer::new(|_, body| match body["chat_id"].as_i64() {
+            Some(-1) => json!({
+                "ok": false,
+                "error_code": 400,
+                "description": "Bad Request: group chat was upgraded to a supergroup chat",
+                "parameters": {"migrate_to_chat_id": -1001}
+            }),
+            Some(-1001) => ok_response(json!({
+                "message_id": 1,
+                "date": 0,
+                "from": {"id": 100, "is_bot": true, "first_name": "bot"},
+                "chat": {"id": -1001, "type": "supergroup", "title": "test"},
+                "text": body["text"]
+            })),
+            _ => error_response(),
+        })
+    }
+
+    #[tokio::test]
+    async fn migrated() {
+        let server = create_server();
+        let message = execute_with_migration(&server.api(), -1, |chat_id| SendMessage::new(chat_id, "text"))
+            .await
+            .unwrap();
+        assert_eq!(message.get_chat_id(), -1001);
+        assert_eq!(
+            server
+                .requests()
+                .into_iter()
+                .map(|request| (request.method, request.body))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("sendMessage"), json!({"chat_id": -1, "text": "text"})),
+                (String::from("sendMessage"), json!({"chat_id": -1001, "text": "text"})),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn not_migrated() {
+        let server = create_server();
+        let api = server.api();
+        let message = execute_with_migration(&api, -1001, |chat_id| SendMessage::new(chat_id, "text"))
+            .await
+            .unwrap();
+        assert_eq!(message.get_chat_id(), -1001);
+        // other errors are not retried
+        assert!(
+            execute_with_migration(&api, -2, |chat_id| SendMessage::new(chat_id, "text"))
+                .await
+                .is_err()
+        );
+        assert_eq!(server.methods(), vec!["sendMessage", "sendMessage"]);
+    }
+}
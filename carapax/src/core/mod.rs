@@ -1,11 +1,48 @@
+mod action;
+mod args;
+mod cache;
+mod callback;
+mod chat;
 mod convert;
+mod deeplink;
 mod dispatcher;
+mod edit;
 mod handler;
+mod keyboard;
+mod manager;
+mod migrate;
+mod permission;
+mod photo;
+mod prompt;
+mod queue;
+mod reply;
+mod resolve;
 mod result;
+mod router;
+mod slowmode;
 
 pub use self::{
+    action::{with_chat_action, with_typing},
+    args::{CommandArg, CommandArgsError, FromCommandArgs, ParseCommandArgs, ReplyCommandUsage},
+    callback::{AnswerableCallbackQuery, AutoAnswerCallbackQuery},
+    chat::ChatExt,
     convert::TryFromUpdate,
-    dispatcher::{Dispatcher, ErrorHandler, ErrorPolicy, LoggingErrorHandler},
+    deeplink::{
+        decode_start_param, encode_start_param, get_start_param, start_link, StartParamError, MAX_START_PARAM_LEN,
+    },
+    dispatcher::{Dispatcher, ErrorHandler, ErrorPolicy, HandlerPanic, LoggingErrorHandler},
+    edit::{EditTextCache, ThrottledEdit},
     handler::Handler,
+    keyboard::InlineKeyboardMarkupExt,
+    manager::{BotManager, BotManagerHandle},
+    migrate::execute_with_migration,
+    permission::PermissionChecker,
+    photo::PhotoSizes,
+    prompt::is_reply_to_bot,
+    queue::ChatQueue,
+    reply::{AwaitReply, AwaitReplyError},
+    resolve::{ChatResolver, ResolveChatError},
     result::{HandlerError, HandlerResult},
+    router::Router,
+    slowmode::SlowModeLimiter,
 };
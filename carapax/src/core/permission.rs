@@ -0,0 +1,273 @@
+use std::sync::Arc;
+use tgbot::{
+    methods::{GetChat, GetChatMember, GetMe},
+    types::{Chat, ChatId, ChatMember, Integer},
+    Api, ExecuteError,
+};
+use tokio::sync::Mutex;
+
+const FORBIDDEN: i64 = 403;
+
+/// Checks whether the bot is allowed to send text messages to a chat
+///
+/// Takes into account status of the bot in the chat (kicked, left, restricted),
+/// default permissions of a group and admin rights in a channel.
+///
+/// ID of the bot is obtained using `getMe` method once and shared between clones.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{methods::SendMessage, Api, ExecuteError, PermissionChecker};
+///
+/// async fn notify(api: &Api, checker: &PermissionChecker, chat_id: i64) -> Result<(), ExecuteError> {
+///     if checker.can_send_messages(chat_id).await? {
+///         api.execute(SendMessage::new(chat_id, "Hello!")).await?;
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct PermissionChecker {
+    api: Api,
+    bot_id: Arc<Mutex<Option<Integer>>>,
+}
+
+impl PermissionChecker {
+    /// Creates a new checker
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client used to send requests
+    pub fn new(api: Api) -> Self {
+        Self {
+            api,
+            bot_id: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns true if the bot can send text messages to a chat
+    ///
+    /// Returns `false` when Telegram forbids access to the chat (e.g. the bot was kicked).
+    ///
+    /// Private chats are always considered writable,
+    /// since there is no way to know whether a user has blocked the bot before sending.
+    ///
+    /// Note that `getChat` and `getChatMember` requests are sent on every call.
+    ///
+    /// # Arguments
+    ///
+    /// * chat_id - ID of a chat
+    pub async fn can_send_messages<C>(&self, chat_id: C) -> Result<bool, ExecuteError>
+    where
+        C: Into<ChatId>,
+    {
+        let chat_id = chat_id.into();
+        let result = async {
+            let chat = self.api.execute(GetChat::new(chat_id.clone())).await?;
+            if let Chat::Private(_) = chat {
+                return Ok(true);
+            }
+            let bot_id = self.get_bot_id().await?;
+            let member = self.api.execute(GetChatMember::new(chat_id, bot_id)).await?;
+            Ok(is_allowed_to_send(&chat, &member))
+        }
+        .await;
+        match result {
+            Err(ExecuteError::Response(ref err)) if err.error_code() == Some(FORBIDDEN) => Ok(false),
+            result => result,
+        }
+    }
+
+    async fn get_bot_id(&self) -> Result<Integer, ExecuteError> {
+        // lock is held while a request is sent, so concurrent calls send a single request
+        let mut bot_id = self.bot_id.lock().await;
+        match *bot_id {
+            Some(bot_id) => Ok(bot_id),
+            None => {
+                let me = self.api.execute(GetMe).await?;
+                *bot_id = Some(me.id);
+                Ok(me.id)
+            }
+        }
+    }
+}
+
+fn is_allowed_to_send(chat: &Chat, member: &ChatMember) -> bool {
+    match member {
+        ChatMember::Creator(_) => true,
+        ChatMember::Administrator(admin) => match chat {
+            Chat::Channel(_) => admin.can_post_messages.unwrap_or(false),
+            _ => true,
+        },
+        ChatMember::Kicked(_) | ChatMember::Left(_) => false,
+        ChatMember::Restricted(restricted) => restricted.is_member && restricted.can_send_messages,
+        ChatMember::Member(_) => match chat {
+            Chat::Channel(_) => false,
+            Chat::Group(group) => group
+                .permissions
+                .as_ref()
+                .and_then(|permissions| permissions.can_send_messages)
+                .unwrap_or(true),
+            Chat::Supergroup(supergroup) => supergroup
+                .permissions
+                .as_ref()
+                .and_then(|permissions| permissions.can_send_messages)
+                .unwrap_or(true),
+            Chat::Private(_) => true,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{error_response, ok_response, MockServer};
+    use serde_json::{json, Value};
+
+    fn create_server() -> MockServer {
+        MockServer::new(|method, body| match (method, body["chat_id"].as_i64()) {
+            ("getChat", Some(1)) => ok_response(json!({"id": 1, "type": "private", "first_name": "test"})),
+            ("getChat", Some(-1)) => ok_response(json!({"id": -1, "type": "supergroup", "title": "test"})),
+            ("getChat", Some(-2)) => json!({
+                "ok": false,
+                "error_code": 403,
+                "description": "Forbidden: bot was kicked from the supergroup chat"
+            }),
+            ("getMe", None) => ok_response(json!({
+                "id": 100,
+                "is_bot": true,
+                "first_name": "bot",
+                "username": "test_bot",
+                "can_join_groups": true,
+                "can_read_all_group_messages": false,
+                "supports_inline_queries": false
+            })),
+            ("getChatMember", Some(-1)) => ok_response(json!({
+                "user": {"id": body["user_id"], "is_bot": true, "first_name": "bot"},
+                "status": "member"
+            })),
+            _ => error_response(),
+        })
+    }
+
+    #[tokio::test]
+    async fn can_send_messages() {
+        let server = create_server();
+        let checker = PermissionChecker::new(server.api());
+        assert!(checker.can_send_messages(-1).await.unwrap());
+        // bot ID is cached
+        assert!(checker.can_send_messages(-1).await.unwrap());
+        assert_eq!(
+            server
+                .requests()
+                .into_iter()
+                .map(|request| (request.method, request.body))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("getChat"), json!({"chat_id": -1})),
+                (String::from("getMe"), Value::Null),
+                (String::from("getChatMember"), json!({"chat_id": -1, "user_id": 100})),
+                (String::from("getChat"), json!({"chat_id": -1})),
+                (String::from("getChatMember"), json!({"chat_id": -1, "user_id": 100})),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn private_chat() {
+        let server = create_server();
+        let checker = PermissionChecker::new(server.api());
+        assert!(checker.can_send_messages(1).await.unwrap());
+        assert_eq!(server.methods(), vec!["getChat"]);
+    }
+
+    #[tokio::test]
+    async fn forbidden() {
+        let server = create_server();
+        let checker = PermissionChecker::new(server.api());
+        assert!(!checker.can_send_messages(-2).await.unwrap());
+        assert_eq!(server.methods(), vec!["getChat"]);
+        // other errors are returned
+        assert!(checker.can_send_messages(-3).await.is_err());
+    }
+
+    fn create_chat(kind: &str, permissions: Option<Value>) -> Chat {
+        let mut chat = json!({
+            "id": 1,
+            "type": kind,
+            "title": "test",
+            "all_members_are_administrators": false
+        });
+        if let Some(permissions) = permissions {
+            chat["permissions"] = permissions;
+        }
+        serde_json::from_value(chat).unwrap()
+    }
+
+    fn create_member(status: &str, extra: Value) -> ChatMember {
+        let mut member = json!({
+            "user": {"id": 1, "is_bot": true, "first_name": "bot"},
+            "status": status
+        });
+        for (key, value) in extra.as_object().unwrap() {
+            member[key] = value.clone();
+        }
+        serde_json::from_value(member).unwrap()
+    }
+
+    #[test]
+    fn allowed_to_send() {
+        let group = create_chat("supergroup", None);
+        let readonly_group = create_chat("supergroup", Some(json!({"can_send_messages": false})));
+        let channel = create_chat("channel", None);
+
+        let member = create_member("member", json!({}));
+        assert!(is_allowed_to_send(&group, &member));
+        assert!(!is_allowed_to_send(&readonly_group, &member));
+        assert!(!is_allowed_to_send(&channel, &member));
+
+        let creator = create_member("creator", json!({}));
+        assert!(is_allowed_to_send(&readonly_group, &creator));
+
+        for (can_post_messages, expected) in &[(true, true), (false, false)] {
+            let admin = create_member(
+                "administrator",
+                json!({
+                    "can_be_edited": false,
+                    "can_change_info": false,
+                    "can_post_messages": can_post_messages,
+                    "can_delete_messages": false,
+                    "can_invite_users": false,
+                    "can_restrict_members": false,
+                    "can_promote_members": false
+                }),
+            );
+            assert_eq!(is_allowed_to_send(&channel, &admin), *expected);
+            assert!(is_allowed_to_send(&readonly_group, &admin));
+        }
+
+        for (can_send_messages, expected) in &[(true, true), (false, false)] {
+            let restricted = create_member(
+                "restricted",
+                json!({
+                    "until_date": 0,
+                    "can_change_info": false,
+                    "can_invite_users": false,
+                    "can_send_messages": can_send_messages,
+                    "can_send_polls": false,
+                    "can_send_media_messages": false,
+                    "can_send_other_messages": false,
+                    "can_add_web_page_previews": false,
+                    "is_member": true
+                }),
+            );
+            assert_eq!(is_allowed_to_send(&group, &restricted), *expected);
+        }
+
+        let kicked = create_member("kicked", json!({"until_date": 0}));
+        assert!(!is_allowed_to_send(&group, &kicked));
+        let left = create_member("left", json!({}));
+        assert!(!is_allowed_to_send(&group, &left));
+    }
+}
@@ -0,0 +1,147 @@
+use tgbot::types::{Integer, Message, MessageData, PhotoSize};
+
+/// Available sizes of a photo
+///
+/// Telegram sends several sizes for each photo,
+/// use this wrapper to pick a suitable one.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{types::Message, PhotoSizes};
+///
+/// fn get_file_id(message: &Message) -> Option<&str> {
+///     // a photo which can be downloaded using getFile (up to 20 MB)
+///     let photo = PhotoSizes::from_message(message)?.best_under(20 * 1024 * 1024)?;
+///     Some(&photo.file_id)
+/// }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct PhotoSizes<'a> {
+    sizes: &'a [PhotoSize],
+}
+
+impl<'a> PhotoSizes<'a> {
+    /// Creates a new wrapper
+    ///
+    /// # Arguments
+    ///
+    /// * sizes - Sizes of a photo
+    pub fn new(sizes: &'a [PhotoSize]) -> Self {
+        Self { sizes }
+    }
+
+    /// Returns sizes of a photo sent in a message
+    ///
+    /// Returns None if message does not contain a photo
+    pub fn from_message(message: &'a Message) -> Option<Self> {
+        match message.data {
+            MessageData::Photo { ref data, .. } => Some(Self::new(data)),
+            _ => None,
+        }
+    }
+
+    /// Returns all sizes
+    pub fn as_slice(&self) -> &'a [PhotoSize] {
+        self.sizes
+    }
+
+    /// Returns a size with the largest number of pixels
+    pub fn largest(&self) -> Option<&'a PhotoSize> {
+        self.sizes.iter().max_by_key(|size| get_area(size))
+    }
+
+    /// Returns a size with the smallest number of pixels
+    pub fn smallest(&self) -> Option<&'a PhotoSize> {
+        self.sizes.iter().min_by_key(|size| get_area(size))
+    }
+
+    /// Returns the largest size which file is not bigger than given number of bytes
+    ///
+    /// Sizes without a known file size are skipped
+    ///
+    /// # Arguments
+    ///
+    /// * max_bytes - Max size of a file
+    pub fn best_under(&self, max_bytes: Integer) -> Option<&'a PhotoSize> {
+        self.sizes
+            .iter()
+            .filter(|size| size.file_size.map(|file_size| file_size <= max_bytes).unwrap_or(false))
+            .max_by_key(|size| get_area(size))
+    }
+
+    /// Returns the smallest size which is not less than given dimensions
+    ///
+    /// # Arguments
+    ///
+    /// * min_width - Min width in pixels
+    /// * min_height - Min height in pixels
+    pub fn smallest_above(&self, min_width: Integer, min_height: Integer) -> Option<&'a PhotoSize> {
+        self.sizes
+            .iter()
+            .filter(|size| size.width >= min_width && size.height >= min_height)
+            .min_by_key(|size| get_area(size))
+    }
+}
+
+fn get_area(size: &PhotoSize) -> Integer {
+    size.width * size.height
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_message() -> Message {
+        serde_json::from_value(serde_json::json!({
+            "message_id": 1111,
+            "date": 0,
+            "from": {"id": 1, "is_bot": false, "first_name": "test"},
+            "chat": {"id": 1, "type": "private", "first_name": "test"},
+            "photo": [
+                {"file_id": "medium", "file_unique_id": "medium", "width": 320, "height": 240, "file_size": 20000},
+                {"file_id": "small", "file_unique_id": "small", "width": 90, "height": 67, "file_size": 1500},
+                {"file_id": "large", "file_unique_id": "large", "width": 1280, "height": 960},
+                {"file_id": "big", "file_unique_id": "big", "width": 800, "height": 600, "file_size": 90000}
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn get_id(size: Option<&PhotoSize>) -> Option<&str> {
+        size.map(|size| size.file_id.as_str())
+    }
+
+    #[test]
+    fn pick_size() {
+        let message = create_message();
+        let sizes = PhotoSizes::from_message(&message).unwrap();
+        assert_eq!(sizes.as_slice().len(), 4);
+        assert_eq!(get_id(sizes.largest()), Some("large"));
+        assert_eq!(get_id(sizes.smallest()), Some("small"));
+        // file size of the largest photo is unknown
+        assert_eq!(get_id(sizes.best_under(100_000)), Some("big"));
+        assert_eq!(get_id(sizes.best_under(20000)), Some("medium"));
+        assert_eq!(get_id(sizes.best_under(1000)), None);
+        assert_eq!(get_id(sizes.smallest_above(300, 200)), Some("medium"));
+        assert_eq!(get_id(sizes.smallest_above(321, 200)), Some("big"));
+        assert_eq!(get_id(sizes.smallest_above(2000, 2000)), None);
+
+        let empty = PhotoSizes::new(&[]);
+        assert!(empty.largest().is_none());
+        assert!(empty.smallest().is_none());
+    }
+
+    #[test]
+    fn not_a_photo() {
+        let message: Message = serde_json::from_value(serde_json::json!({
+            "message_id": 1111,
+            "date": 0,
+            "from": {"id": 1, "is_bot": false, "first_name": "test"},
+            "chat": {"id": 1, "type": "private", "first_name": "test"},
+            "text": "test"
+        }))
+        .unwrap();
+        assert!(PhotoSizes::from_message(&message).is_none());
+    }
+}
@@ -0,0 +1,80 @@
+use tgbot::types::{Integer, Message};
+
+/// Returns true if a message is a reply to a message sent by the bot
+///
+/// Use it to match an answer to a prompt sent with
+/// [ForceReply](types/struct.ForceReply.html) markup.
+///
+/// # Arguments
+///
+/// * message - An incoming message
+/// * bot_id - ID of the bot, returned by `getMe` method
+///
+/// # Example
+///
+/// ```
+/// use carapax::{
+///     is_reply_to_bot,
+///     methods::SendMessage,
+///     types::{ForceReply, Integer, Message},
+///     Api, ExecuteError,
+/// };
+///
+/// async fn ask_name(api: &Api, chat_id: Integer) -> Result<(), ExecuteError> {
+///     api.execute(SendMessage::new(chat_id, "What's your name?").reply_markup(ForceReply::new(true)))
+///         .await?;
+///     Ok(())
+/// }
+///
+/// fn get_name(bot_id: Integer, message: &Message) -> Option<&str> {
+///     if is_reply_to_bot(message, bot_id) {
+///         message.get_text().map(|text| text.data.as_str())
+///     } else {
+///         None
+///     }
+/// }
+/// ```
+pub fn is_reply_to_bot(message: &Message, bot_id: Integer) -> bool {
+    message
+        .reply_to
+        .as_ref()
+        .and_then(|prompt| prompt.get_user())
+        .map(|user| user.id == bot_id)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn create_message(reply_to_user_id: Option<Integer>) -> Message {
+        let chat = json!({"id": 1, "type": "private", "first_name": "test"});
+        let mut message = json!({
+            "message_id": 2,
+            "date": 0,
+            "from": {"id": 1, "is_bot": false, "first_name": "test"},
+            "chat": chat,
+            "text": "answer"
+        });
+        if let Some(user_id) = reply_to_user_id {
+            message["reply_to_message"] = json!({
+                "message_id": 1,
+                "date": 0,
+                "from": {"id": user_id, "is_bot": user_id == 100, "first_name": "user"},
+                "chat": chat,
+                "text": "prompt"
+            });
+        }
+        serde_json::from_value(message).unwrap()
+    }
+
+    #[test]
+    fn reply_to_bot() {
+        assert!(is_reply_to_bot(&create_message(Some(100)), 100));
+        // reply to another user
+        assert!(!is_reply_to_bot(&create_message(Some(2)), 100));
+        // not a reply
+        assert!(!is_reply_to_bot(&create_message(None), 100));
+    }
+}
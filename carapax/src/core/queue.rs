@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tgbot::{
+    methods::Method,
+    types::{Integer, Message},
+    Api, ExecuteError,
+};
+use tokio::sync::oneshot;
+
+type Tails = HashMap<Integer, (u64, oneshot::Receiver<()>)>;
+
+/// Sends messages to the same chat one by one in order of calls
+///
+/// Separate tasks which send messages to a chat concurrently may race,
+/// so messages are delivered out of order.
+/// A queue sends a next message to a chat only when a previous one is sent (or failed),
+/// while messages to different chats are sent in parallel.
+///
+/// A position in a queue is taken when [execute()](#method.execute) is called,
+/// not when a returned future is polled, so messages are sent in order of calls
+/// even when futures are spawned as separate tasks.
+/// Queues are shared between clones.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{methods::SendMessage, types::Integer, ChatQueue};
+///
+/// fn send_all(queue: &ChatQueue, chat_id: Integer, texts: Vec<String>) {
+///     for text in texts {
+///         let send = queue.execute(chat_id, |chat_id| SendMessage::new(chat_id, text));
+///         tokio::spawn(async move {
+///             if let Err(err) = send.await {
+///                 log::error!("Failed to send a message: {}", err);
+///             }
+///         });
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ChatQueue {
+    api: Api,
+    tails: Arc<Mutex<Tails>>,
+    counter: Arc<AtomicU64>,
+}
+
+impl ChatQueue {
+    /// Creates a new queue
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client used to send requests
+    pub fn new(api: Api) -> Self {
+        Self {
+            api,
+            tails: Arc::new(Mutex::new(HashMap::new())),
+            counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Sends a message after all messages to the chat queued before
+    ///
+    /// # Arguments
+    ///
+    /// * chat_id - ID of a chat
+    /// * build - Returns a method which sends a message to given chat
+    pub fn execute<F, M>(&self, chat_id: Integer, build: F) -> impl Future<Output = Result<Message, ExecuteError>>
+    where
+        F: FnOnce(Integer) -> M,
+        M: Method<Response = Message>,
+    {
+        let mut turn = self.enqueue(chat_id);
+        let api = self.api.clone();
+        async move {
+            turn.wait().await;
+            api.execute(build(chat_id)).await
+        }
+    }
+
+    fn enqueue(&self, chat_id: Integer) -> Turn {
+        let id = self.counter.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        let previous = self
+            .tails
+            .lock()
+            .expect("Chat queue lock poisoned")
+            .insert(chat_id, (id, rx))
+            .map(|(_, rx)| rx);
+        Turn {
+            tails: self.tails.clone(),
+            chat_id,
+            id,
+            previous,
+            _done: tx,
+        }
+    }
+}
+
+/// A position in a queue of a chat
+///
+/// Next position is released when dropped
+struct Turn {
+    tails: Arc<Mutex<Tails>>,
+    chat_id: Integer,
+    id: u64,
+    previous: Option<oneshot::Receiver<()>>,
+    _done: oneshot::Sender<()>,
+}
+
+impl Turn {
+    async fn wait(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            // sender is dropped when a previous message is sent or cancelled
+            let _ = previous.await;
+        }
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        if let Ok(mut tails) = self.tails.lock() {
+            // a queue of a chat is removed when it is empty
+            if tails.get(&self.chat_id).map(|(id, _)| *id == self.id).unwrap_or(false) {
+                tails.remove(&self.chat_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{ok_response, MockServer};
+    use serde_json::json;
+    use tgbot::methods::SendMessage;
+
+    fn get_texts(server: &MockServer) -> Vec<String> {
+        server
+            .requests()
+            .into_iter()
+            .map(|request| request.body["text"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn order() {
+        // response to the first message is held until released
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(Some(release_rx)));
+        let mut server = MockServer::with_async(move |_, body| {
+            let release_rx = release_rx.clone();
+            async move {
+                if body["text"] == "first" {
+                    let release_rx = release_rx.lock().unwrap().take().unwrap();
+                    release_rx.await.unwrap();
+                }
+                ok_response(json!({
+                    "message_id": 1,
+                    "date": 0,
+                    "from": {"id": 100, "is_bot": true, "first_name": "bot"},
+                    "chat": {"id": body["chat_id"], "type": "private", "first_name": "test"},
+                    "text": body["text"]
+                }))
+            }
+        });
+        let queue = ChatQueue::new(server.api());
+        let first = queue.execute(1, |chat_id| SendMessage::new(chat_id, "first"));
+        let second = queue.execute(1, |chat_id| SendMessage::new(chat_id, "second"));
+        let other = queue.execute(2, |chat_id| SendMessage::new(chat_id, "other"));
+        let check = async {
+            server.wait_requests(2).await;
+            // another chat is not blocked, second message waits for the first one
+            let mut texts = get_texts(&server);
+            texts.sort();
+            assert_eq!(texts, vec!["first", "other"]);
+            release_tx.send(()).unwrap();
+        };
+        // futures are polled in a different order than they were queued
+        let (second, first, other, ()) = tokio::join!(second, first, other, check);
+        assert_eq!(first.unwrap().get_chat_id(), 1);
+        assert_eq!(second.unwrap().get_chat_id(), 1);
+        assert_eq!(other.unwrap().get_chat_id(), 2);
+        assert_eq!(get_texts(&server).last().map(String::as_str), Some("second"));
+        assert!(queue.tails.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,391 @@
+use crate::core::{handler::Handler, result::HandlerResult};
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tgbot::{
+    methods::SendMessage,
+    types::{Integer, Message, Update, UpdateKind},
+    Api, ExecuteError,
+};
+use tokio::{sync::oneshot, time::timeout};
+
+type WaiterKey = (Integer, Integer);
+type Waiters = HashMap<WaiterKey, (u64, oneshot::Sender<Message>)>;
+
+/// Allows to wait for a next message from a user
+///
+/// Add it to a dispatcher before other handlers:
+/// a new message from a user who is being waited for is passed to a waiting task
+/// and propagation stops; other updates (including edited messages) are passed to next handlers.
+///
+/// Note that updates are processed sequentially,
+/// so a handler must not wait for a reply directly, since the reply will never be dispatched.
+/// Spawn a task which runs a conversation instead.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{handler, types::Command, Api, AwaitReply, Dispatcher};
+/// use std::time::Duration;
+///
+/// struct Context {
+///     api: Api,
+///     await_reply: AwaitReply,
+/// }
+///
+/// #[handler(command = "/start")]
+/// async fn handle_start(context: &Context, command: Command) {
+///     let (api, await_reply) = (context.api.clone(), context.await_reply.clone());
+///     let message = command.get_message();
+///     let chat_id = message.get_chat_id();
+///     let user_id = message.get_user().map(|user| user.id).unwrap();
+///     tokio::spawn(async move {
+///         let timeout = Duration::from_secs(60);
+///         match await_reply.ask(&api, chat_id, user_id, "What's your name?", timeout).await {
+///             Ok(reply) => println!("Name: {:?}", reply.get_text()),
+///             Err(err) => println!("No answer: {}", err),
+///         }
+///     });
+/// }
+///
+/// let api = Api::new("token").unwrap();
+/// let await_reply = AwaitReply::new();
+/// let mut dispatcher = Dispatcher::new(Context {
+///     api,
+///     await_reply: await_reply.clone(),
+/// });
+/// dispatcher.add_handler(await_reply);
+/// dispatcher.add_handler(handle_start);
+/// ```
+#[derive(Clone, Default)]
+pub struct AwaitReply {
+    waiters: Arc<Mutex<Waiters>>,
+    counter: Arc<AtomicU64>,
+}
+
+impl AwaitReply {
+    /// Creates a new AwaitReply
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends a text and waits for a next message from a user in a chat
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client used to send a question
+    /// * chat_id - ID of a chat
+    /// * user_id - ID of a user
+    /// * text - Text of a question
+    /// * timeout - How long to wait for a reply
+    pub async fn ask<T>(
+        &self,
+        api: &Api,
+        chat_id: Integer,
+        user_id: Integer,
+        text: T,
+        timeout: Duration,
+    ) -> Result<Message, AwaitReplyError>
+    where
+        T: Into<String>,
+    {
+        // register before sending, so a fast reply is not missed
+        let waiter = self.register(chat_id, user_id);
+        api.execute(SendMessage::new(chat_id, text)).await?;
+        waiter.wait(timeout).await
+    }
+
+    /// Waits for a next message from a user in a chat
+    ///
+    /// A previous wait for the same chat and user is cancelled.
+    ///
+    /// # Arguments
+    ///
+    /// * chat_id - ID of a chat
+    /// * user_id - ID of a user
+    /// * timeout - How long to wait for a reply
+    pub async fn wait(
+        &self,
+        chat_id: Integer,
+        user_id: Integer,
+        timeout: Duration,
+    ) -> Result<Message, AwaitReplyError> {
+        self.register(chat_id, user_id).wait(timeout).await
+    }
+
+    fn register(&self, chat_id: Integer, user_id: Integer) -> Waiter {
+        let id = self.counter.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        let key = (chat_id, user_id);
+        self.waiters
+            .lock()
+            .expect("AwaitReply lock poisoned")
+            .insert(key, (id, tx));
+        Waiter {
+            waiters: self.waiters.clone(),
+            key,
+            id,
+            rx,
+        }
+    }
+
+    fn take_waiter(&self, message: &Message) -> Option<oneshot::Sender<Message>> {
+        let user_id = message.get_user()?.id;
+        self.waiters
+            .lock()
+            .expect("AwaitReply lock poisoned")
+            .remove(&(message.get_chat_id(), user_id))
+            .map(|(_, tx)| tx)
+    }
+}
+
+#[async_trait]
+impl<C> Handler<C> for AwaitReply
+where
+    C: Send + Sync,
+{
+    type Input = Update;
+    type Output = HandlerResult;
+
+    async fn handle(&mut self, _context: &C, input: Self::Input) -> Self::Output {
+        let message = match input.kind {
+            UpdateKind::Message(message) => message,
+            _ => return HandlerResult::Continue,
+        };
+        match self.take_waiter(&message) {
+            Some(tx) => match tx.send(message) {
+                Ok(()) => HandlerResult::Stop,
+                // waiter is gone (e.g. timed out)
+                Err(_) => HandlerResult::Continue,
+            },
+            None => HandlerResult::Continue,
+        }
+    }
+}
+
+/// Removes a waiter when a wait is finished
+struct Waiter {
+    waiters: Arc<Mutex<Waiters>>,
+    key: WaiterKey,
+    id: u64,
+    rx: oneshot::Receiver<Message>,
+}
+
+impl Waiter {
+    async fn wait(mut self, duration: Duration) -> Result<Message, AwaitReplyError> {
+        match timeout(duration, &mut self.rx).await {
+            Ok(Ok(message)) => Ok(message),
+            Ok(Err(_)) => Err(AwaitReplyError::Cancelled),
+            Err(_) => Err(AwaitReplyError::Timeout),
+        }
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        if let Ok(mut waiters) = self.waiters.lock() {
+            // another wait could be registered for the same key
+            if waiters.get(&self.key).map(|(id, _)| *id == self.id).unwrap_or(false) {
+                waiters.remove(&self.key);
+            }
+        }
+    }
+}
+
+/// An error when waiting for a reply
+#[derive(Debug)]
+pub enum AwaitReplyError {
+    /// Another wait for the same chat and user was started
+    Cancelled,
+    /// Failed to send a question
+    Execute(ExecuteError),
+    /// User did not reply in time
+    Timeout,
+}
+
+impl From<ExecuteError> for AwaitReplyError {
+    fn from(err: ExecuteError) -> Self {
+        AwaitReplyError::Execute(err)
+    }
+}
+
+impl Error for AwaitReplyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AwaitReplyError::Execute(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AwaitReplyError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AwaitReplyError::Cancelled => write!(out, "wait is cancelled"),
+            AwaitReplyError::Execute(err) => write!(out, "failed to send a question: {}", err),
+            AwaitReplyError::Timeout => write!(out, "reply timed out"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{error_response, ok_response, MockServer};
+    use futures_util::future::join;
+    use serde_json::json;
+
+    fn create_server() -> MockServer {
+        MockServer::new(|method, body| match (method, body["chat_id"].as_i64()) {
+            ("sendMessage", Some(1)) => ok_response(json!({
+                "message_id": 1,
+                "date": 0,
+                "from": {"id": 100, "is_bot": true, "first_name": "bot"},
+                "chat": {"id": 1, "type": "supergroup", "title": "test"},
+                "text": body["text"]
+            })),
+            _ => error_response(),
+        })
+    }
+
+    fn create_update(kind: &str, chat_id: Integer, user_id: Integer) -> Update {
+        let mut update = serde_json::json!({"update_id": 1});
+        update[kind] = serde_json::json!({
+            "message_id": 1111,
+            "date": 0,
+            "from": {"id": user_id, "is_bot": false, "first_name": "test"},
+            "chat": {"id": chat_id, "type": "supergroup", "title": "test"},
+            "text": "reply"
+        });
+        serde_json::from_value(update).unwrap()
+    }
+
+    fn create_message(chat_id: Integer, user_id: Integer) -> Update {
+        create_update("message", chat_id, user_id)
+    }
+
+    #[tokio::test]
+    async fn wait_reply() {
+        let await_reply = AwaitReply::new();
+        let mut handler = await_reply.clone();
+        let wait = await_reply.wait(1, 2, Duration::from_secs(60));
+        let dispatch = async {
+            // message from another user
+            assert!(matches!(
+                handler.handle(&(), create_message(1, 3)).await,
+                HandlerResult::Continue
+            ));
+            assert!(matches!(
+                handler.handle(&(), create_message(1, 2)).await,
+                HandlerResult::Stop
+            ));
+        };
+        let (reply, ()) = join(wait, dispatch).await;
+        assert_eq!(reply.unwrap().get_user().unwrap().id, 2);
+        assert!(await_reply.waiters.lock().unwrap().is_empty());
+
+        // nobody waits
+        assert!(matches!(
+            handler.handle(&(), create_message(1, 2)).await,
+            HandlerResult::Continue
+        ));
+    }
+
+    #[tokio::test]
+    async fn edited_message_ignored() {
+        let await_reply = AwaitReply::new();
+        let mut handler = await_reply.clone();
+        let wait = await_reply.wait(1, 2, Duration::from_millis(50));
+        let dispatch = async {
+            assert!(matches!(
+                handler.handle(&(), create_update("edited_message", 1, 2)).await,
+                HandlerResult::Continue
+            ));
+        };
+        match join(wait, dispatch).await {
+            (Err(AwaitReplyError::Timeout), ()) => {}
+            (result, ()) => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn wait_timeout() {
+        let await_reply = AwaitReply::new();
+        match await_reply.wait(1, 2, Duration::from_millis(10)).await {
+            Err(AwaitReplyError::Timeout) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        assert!(await_reply.waiters.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn wait_cancelled() {
+        let await_reply = AwaitReply::new();
+        let first = await_reply.register(1, 2);
+        let second = await_reply.register(1, 2);
+        match first.wait(Duration::from_secs(60)).await {
+            Err(AwaitReplyError::Cancelled) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        // second wait is still registered
+        assert_eq!(await_reply.waiters.lock().unwrap().len(), 1);
+        drop(second);
+        assert!(await_reply.waiters.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn ask() {
+        let mut server = create_server();
+        let api = server.api();
+        let await_reply = AwaitReply::new();
+        let mut handler = await_reply.clone();
+        let ask = await_reply.ask(&api, 1, 2, "What's your name?", Duration::from_secs(60));
+        let dispatch = async {
+            server.wait_requests(1).await;
+            assert!(matches!(
+                handler.handle(&(), create_message(1, 2)).await,
+                HandlerResult::Stop
+            ));
+        };
+        let (reply, ()) = join(ask, dispatch).await;
+        assert_eq!(reply.unwrap().get_user().unwrap().id, 2);
+        assert!(await_reply.waiters.lock().unwrap().is_empty());
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "sendMessage");
+        assert_eq!(requests[0].body, json!({"chat_id": 1, "text": "What's your name?"}));
+    }
+
+    #[tokio::test]
+    async fn ask_timeout() {
+        let server = create_server();
+        let await_reply = AwaitReply::new();
+        match await_reply
+            .ask(&server.api(), 1, 2, "question", Duration::from_millis(10))
+            .await
+        {
+            Err(AwaitReplyError::Timeout) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        assert!(await_reply.waiters.lock().unwrap().is_empty());
+
+        // waiter is removed when a question is not sent
+        match await_reply
+            .ask(&server.api(), 3, 2, "question", Duration::from_secs(60))
+            .await
+        {
+            Err(AwaitReplyError::Execute(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        assert!(await_reply.waiters.lock().unwrap().is_empty());
+        assert_eq!(server.methods(), vec!["sendMessage", "sendMessage"]);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::core::cache::TtlCache;
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tgbot::{
+    methods::GetChat,
+    types::{Chat, Integer},
+    Api, ExecuteError,
+};
+
+const DEFAULT_CAPACITY: usize = 1000;
+const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+/// Resolves `@username` of a chat into a numeric ID
+///
+/// Uses `getChat` method and caches results,
+/// so subsequent calls for the same username do not send requests.
+/// Cache is shared between clones.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{methods::SendMessage, Api, ChatResolver, ResolveChatError};
+///
+/// async fn send(api: &Api, resolver: &ChatResolver) -> Result<(), ResolveChatError> {
+///     let chat_id = resolver.resolve("@username").await?;
+///     api.execute(SendMessage::new(chat_id, "Hello!")).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ChatResolver {
+    api: Api,
+    capacity: usize,
+    ttl: Duration,
+    cache: Arc<Mutex<TtlCache<String, Integer>>>,
+}
+
+impl ChatResolver {
+    /// Creates a new resolver
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client used to send `getChat` requests
+    pub fn new(api: Api) -> Self {
+        Self {
+            api,
+            capacity: DEFAULT_CAPACITY,
+            ttl: DEFAULT_TTL,
+            cache: Arc::new(Mutex::new(TtlCache::default())),
+        }
+    }
+
+    /// Sets max number of cached usernames
+    ///
+    /// Default value is 1000
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets how long a resolved ID is cached
+    ///
+    /// Default value is 1 hour
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Returns ID of a chat
+    ///
+    /// # Arguments
+    ///
+    /// * username - Username of a chat, leading `@` is optional
+    pub async fn resolve(&self, username: &str) -> Result<Integer, ResolveChatError> {
+        let username = normalize_username(username);
+        if username.is_empty() {
+            return Err(ResolveChatError::NotFound(username));
+        }
+        if let Some(chat_id) = self.get_cached(&username) {
+            return Ok(chat_id);
+        }
+        let chat = match self.api.execute(GetChat::new(format!("@{}", username))).await {
+            Ok(chat) => chat,
+            Err(ExecuteError::Response(ref err)) if is_not_found(err.description()) => {
+                return Err(ResolveChatError::NotFound(username))
+            }
+            Err(err) => return Err(ResolveChatError::Execute(err)),
+        };
+        let chat_id = match chat {
+            Chat::Channel(chat) => chat.id,
+            Chat::Group(chat) => chat.id,
+            Chat::Private(chat) => chat.id,
+            Chat::Supergroup(chat) => chat.id,
+        };
+        self.set_cached(username, chat_id);
+        Ok(chat_id)
+    }
+
+    fn get_cached(&self, username: &str) -> Option<Integer> {
+        let cache = self.cache.lock().expect("Chat resolver lock poisoned");
+        cache.get(username, self.ttl).copied()
+    }
+
+    fn set_cached(&self, username: String, chat_id: Integer) {
+        let mut cache = self.cache.lock().expect("Chat resolver lock poisoned");
+        cache.insert(username, chat_id, self.capacity, self.ttl);
+    }
+}
+
+fn normalize_username(username: &str) -> String {
+    // usernames are case insensitive
+    username.trim_start_matches('@').to_lowercase()
+}
+
+fn is_not_found(description: &str) -> bool {
+    description.to_lowercase().contains("chat not found")
+}
+
+/// An error when resolving a chat ID
+#[derive(Debug)]
+pub enum ResolveChatError {
+    /// Failed to send `getChat` request
+    Execute(ExecuteError),
+    /// Chat with given username does not exist
+    NotFound(String),
+}
+
+impl From<ExecuteError> for ResolveChatError {
+    fn from(err: ExecuteError) -> Self {
+        ResolveChatError::Execute(err)
+    }
+}
+
+impl Error for ResolveChatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ResolveChatError::Execute(err) => Some(err),
+            ResolveChatError::NotFound(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ResolveChatError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolveChatError::Execute(err) => write!(out, "failed to get chat: {}", err),
+            ResolveChatError::NotFound(username) => write!(out, "chat @{} not found", username),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{error_response, ok_response, MockServer};
+    use serde_json::json;
+
+    fn create_server() -> MockServer {
+        MockServer::new(|_, body| match body["chat_id"].as_str() {
+            Some("@first") => ok_response(json!({"id": -1001, "type": "channel", "title": "first"})),
+            Some("@missing") => json!({
+                "ok": false,
+                "error_code": 400,
+                "description": "Bad Request: chat not found"
+            }),
+            _ => error_response(),
+        })
+    }
+
+    #[tokio::test]
+    async fn resolve() {
+        let server = create_server();
+        let resolver = ChatResolver::new(server.api());
+        assert_eq!(resolver.resolve("@First").await.unwrap(), -1001);
+        // cached
+        assert_eq!(resolver.resolve("first").await.unwrap(), -1001);
+        match resolver.resolve("@missing").await {
+            Err(ResolveChatError::NotFound(username)) => assert_eq!(username, "missing"),
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        match resolver.resolve("@broken").await {
+            Err(ResolveChatError::Execute(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        match resolver.resolve("@").await {
+            Err(ResolveChatError::NotFound(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        let requests = server.requests();
+        assert!(requests.iter().all(|request| request.method == "getChat"));
+        assert_eq!(
+            requests.iter().map(|request| request.body.clone()).collect::<Vec<_>>(),
+            vec![
+                json!({"chat_id": "@first"}),
+                json!({"chat_id": "@missing"}),
+                json!({"chat_id": "@broken"})
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cache_capacity() {
+        let server = MockServer::with_error();
+        let resolver = ChatResolver::new(server.api()).capacity(2);
+        resolver.set_cached(String::from("first"), 1);
+        resolver.set_cached(String::from("second"), 2);
+        resolver.set_cached(String::from("third"), 3);
+        assert!(resolver.get_cached("first").is_none());
+        assert_eq!(resolver.get_cached("second"), Some(2));
+        assert_eq!(resolver.get_cached("third"), Some(3));
+
+        let resolver = resolver.ttl(Duration::from_secs(0));
+        assert!(resolver.get_cached("second").is_none());
+    }
+}
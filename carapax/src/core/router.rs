@@ -0,0 +1,287 @@
+use crate::core::{convert::ConvertHandler, handler::Handler, result::HandlerResult};
+use async_trait::async_trait;
+use std::convert::TryFrom;
+use tgbot::types::{CallbackQuery, Command, Message, Update, UpdateKind};
+
+type BoxedHandler<C> = Box<dyn Handler<C, Input = Update, Output = HandlerResult> + Send>;
+type BoxedMatcher = Box<dyn Fn(&Update) -> bool + Send + Sync>;
+
+/// Routes an update to the first matching handler
+///
+/// Routes are checked in the same order as they are added.
+/// When no route matches, the router returns [`HandlerResult::Continue`](enum.HandlerResult.html),
+/// so next handlers of a dispatcher will run.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{handler, types::{CallbackQuery, Command, Message, Update}, Dispatcher, Router};
+///
+/// #[handler]
+/// async fn start_handler(_context: &(), _command: Command) {}
+///
+/// #[handler]
+/// async fn vote_handler(_context: &(), _query: CallbackQuery) {}
+///
+/// #[handler]
+/// async fn echo_handler(_context: &(), _message: Message) {}
+///
+/// #[handler]
+/// async fn fallback(_context: &(), _update: Update) {}
+///
+/// let router = Router::new()
+///     .on_command("/start", start_handler)
+///     .on_callback("vote:*", vote_handler)
+///     .on_text(echo_handler)
+///     .on_default(fallback);
+/// let mut dispatcher = Dispatcher::new(());
+/// dispatcher.add_handler(router);
+/// ```
+pub struct Router<C> {
+    routes: Vec<(BoxedMatcher, BoxedHandler<C>)>,
+}
+
+impl<C> Router<C>
+where
+    C: Send + Sync,
+{
+    /// Creates a new router without routes
+    pub fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    /// Adds a handler for a command
+    ///
+    /// # Arguments
+    ///
+    /// * name - Name of a command with leading slash
+    /// * handler - Handler of a command
+    pub fn on_command<N, H>(self, name: N, handler: H) -> Self
+    where
+        N: Into<String>,
+        H: Handler<C, Input = Command> + Send + 'static,
+    {
+        let name = name.into();
+        self.route(
+            move |update| {
+                update
+                    .get_message()
+                    .and_then(|message| Command::try_from(message.clone()).ok())
+                    .map(|command| command.get_name() == name)
+                    .unwrap_or(false)
+            },
+            handler,
+        )
+    }
+
+    /// Adds a handler for a callback query
+    ///
+    /// # Arguments
+    ///
+    /// * pattern - Data of a query; use `prefix*` to match data starting with a prefix
+    /// * handler - Handler of a query
+    pub fn on_callback<P, H>(self, pattern: P, handler: H) -> Self
+    where
+        P: Into<String>,
+        H: Handler<C, Input = CallbackQuery> + Send + 'static,
+    {
+        let pattern = pattern.into();
+        self.route(
+            move |update| match update.kind {
+                UpdateKind::CallbackQuery(ref query) => query
+                    .data
+                    .as_ref()
+                    .map(|data| is_data_matches(&pattern, data))
+                    .unwrap_or(false),
+                _ => false,
+            },
+            handler,
+        )
+    }
+
+    /// Adds a handler for a message which satisfies a predicate
+    ///
+    /// # Arguments
+    ///
+    /// * predicate - Returns true when a message should be handled
+    /// * handler - Handler of a message
+    pub fn on_message<P, H>(self, predicate: P, handler: H) -> Self
+    where
+        P: Fn(&Message) -> bool + Send + Sync + 'static,
+        H: Handler<C, Input = Message> + Send + 'static,
+    {
+        self.route(
+            move |update| update.get_message().map(&predicate).unwrap_or(false),
+            handler,
+        )
+    }
+
+    /// Adds a handler for a text message
+    pub fn on_text<H>(self, handler: H) -> Self
+    where
+        H: Handler<C, Input = Message> + Send + 'static,
+    {
+        self.on_message(|message| message.get_text().is_some(), handler)
+    }
+
+    /// Adds a handler for any update
+    ///
+    /// Add it last, since routes added after it are never checked
+    pub fn on_default<H>(self, handler: H) -> Self
+    where
+        H: Handler<C, Input = Update> + Send + 'static,
+    {
+        self.route(|_| true, handler)
+    }
+
+    fn route<M, H>(mut self, matcher: M, handler: H) -> Self
+    where
+        M: Fn(&Update) -> bool + Send + Sync + 'static,
+        H: Handler<C> + Send + 'static,
+        H::Input: 'static,
+    {
+        self.routes.push((Box::new(matcher), ConvertHandler::boxed(handler)));
+        self
+    }
+}
+
+impl<C> Default for Router<C>
+where
+    C: Send + Sync,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl<C> Handler<C> for Router<C>
+where
+    C: Send + Sync,
+{
+    type Input = Update;
+    type Output = HandlerResult;
+
+    async fn handle(&mut self, context: &C, input: Self::Input) -> Self::Output {
+        for (matcher, handler) in &mut self.routes {
+            if matcher(&input) {
+                return handler.handle(context, input).await;
+            }
+        }
+        HandlerResult::Continue
+    }
+}
+
+fn is_data_matches(pattern: &str, data: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => data.starts_with(prefix),
+        None => data == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::convert::TryFromUpdate;
+    use std::{marker::PhantomData, sync::Mutex};
+
+    type Handled = Mutex<Vec<&'static str>>;
+
+    struct HandlerMock<I> {
+        name: &'static str,
+        input: PhantomData<fn() -> I>,
+    }
+
+    fn mock<I>(name: &'static str) -> HandlerMock<I> {
+        HandlerMock {
+            name,
+            input: PhantomData,
+        }
+    }
+
+    #[async_trait]
+    impl<I> Handler<Handled> for HandlerMock<I>
+    where
+        I: TryFromUpdate + Send + Sync + 'static,
+    {
+        type Input = I;
+        type Output = HandlerResult;
+
+        async fn handle(&mut self, context: &Handled, _input: Self::Input) -> Self::Output {
+            context.lock().unwrap().push(self.name);
+            HandlerResult::Stop
+        }
+    }
+
+    fn create_message_update(text: &str, is_command: bool) -> Update {
+        let mut message = serde_json::json!({
+            "message_id": 1111,
+            "date": 0,
+            "from": {"id": 1, "is_bot": false, "first_name": "test"},
+            "chat": {"id": 1, "type": "private", "first_name": "test"},
+            "text": text
+        });
+        if is_command {
+            message["entities"] = serde_json::json!([{"type": "bot_command", "offset": 0, "length": text.len()}]);
+        }
+        serde_json::from_value(serde_json::json!({"update_id": 1, "message": message})).unwrap()
+    }
+
+    fn create_callback_update(data: &str) -> Update {
+        serde_json::from_value(serde_json::json!({
+            "update_id": 1,
+            "callback_query": {
+                "id": "id",
+                "from": {"id": 1, "is_bot": false, "first_name": "test"},
+                "chat_instance": "instance",
+                "data": data
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn route() {
+        let mut router = Router::new()
+            .on_command("/start", mock::<Command>("start"))
+            .on_callback("vote:*", mock::<CallbackQuery>("vote"))
+            .on_message(
+                |message| message.get_text().map(|text| text.data == "ping").unwrap_or(false),
+                mock::<Message>("ping"),
+            )
+            .on_text(mock::<Message>("text"));
+        let handled = Handled::default();
+        for update in [
+            create_message_update("/start", true),
+            create_message_update("/help", true),
+            create_callback_update("vote:1"),
+            create_message_update("ping", false),
+            create_message_update("pong", false),
+        ]
+        .iter()
+        {
+            router.handle(&handled, update.clone()).await;
+        }
+        // a command is a text message too
+        assert_eq!(*handled.lock().unwrap(), vec!["start", "text", "vote", "ping", "text"]);
+
+        // no route matches
+        let result = router.handle(&handled, create_callback_update("other")).await;
+        assert!(matches!(result, HandlerResult::Continue));
+
+        let mut router = router.on_default(mock::<Update>("default"));
+        let result = router.handle(&handled, create_callback_update("other")).await;
+        assert!(matches!(result, HandlerResult::Stop));
+        assert_eq!(handled.lock().unwrap().last(), Some(&"default"));
+    }
+
+    #[test]
+    fn data_matches() {
+        assert!(is_data_matches("vote:*", "vote:1"));
+        assert!(is_data_matches("vote:*", "vote:"));
+        assert!(!is_data_matches("vote:*", "vot"));
+        assert!(is_data_matches("vote", "vote"));
+        assert!(!is_data_matches("vote", "vote:1"));
+        assert!(is_data_matches("*", "anything"));
+    }
+}
@@ -0,0 +1,359 @@
+use crate::core::cache::TtlCache;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex as SyncMutex, Weak},
+    time::{Duration, Instant},
+};
+use tgbot::{
+    methods::{GetChat, Method},
+    types::{Chat, Integer, Message},
+    Api, ExecuteError,
+};
+use tokio::{sync::Mutex, time::delay_until};
+
+const DEFAULT_CAPACITY: usize = 1000;
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+/// Spaces messages sent to a chat according to its slow mode delay
+///
+/// Slow mode delay of a supergroup is obtained using `getChat` method and cached.
+/// When a delay is unknown (slow mode is disabled, a chat is not a supergroup or a request has failed),
+/// messages are spaced by a default per-chat interval.
+/// Concurrent callers for the same chat wait for a single `getChat` request.
+/// Cache and send times are shared between clones.
+///
+/// Note that [ratelimit](ratelimit/index.html) handlers limit incoming updates,
+/// while this limiter is used for outgoing requests.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{methods::SendMessage, types::Integer, Api, ExecuteError, SlowModeLimiter};
+///
+/// async fn broadcast(limiter: &SlowModeLimiter, chat_id: Integer, texts: Vec<String>) -> Result<(), ExecuteError> {
+///     for text in texts {
+///         limiter.execute(chat_id, |chat_id| SendMessage::new(chat_id, text)).await?;
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct SlowModeLimiter {
+    api: Api,
+    capacity: usize,
+    default_interval: Duration,
+    ttl: Duration,
+    state: Arc<SyncMutex<LimiterState>>,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    // chat_id => slow mode delay
+    delays: TtlCache<Integer, Option<Duration>>,
+    // chat_id => lock of a running getChat request
+    fetch_locks: HashMap<Integer, Weak<Mutex<()>>>,
+    schedule: SendSchedule,
+}
+
+impl SlowModeLimiter {
+    /// Creates a new limiter
+    ///
+    /// # Arguments
+    ///
+    /// * api - Client used to send requests
+    pub fn new(api: Api) -> Self {
+        Self {
+            api,
+            capacity: DEFAULT_CAPACITY,
+            default_interval: DEFAULT_INTERVAL,
+            ttl: DEFAULT_TTL,
+            state: Arc::new(SyncMutex::new(LimiterState::default())),
+        }
+    }
+
+    /// Sets max number of cached chats
+    ///
+    /// Applies to cached slow mode delays and to chats with scheduled messages.
+    /// When a limit is exceeded, a chat which can receive a message soonest is forgotten.
+    ///
+    /// Default value is 1000
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets an interval between messages when slow mode delay is unknown
+    ///
+    /// Default value is 1 second
+    pub fn default_interval(mut self, interval: Duration) -> Self {
+        self.default_interval = interval;
+        self
+    }
+
+    /// Sets how long a slow mode delay is cached
+    ///
+    /// Default value is 1 hour
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Waits until a message can be sent to a chat
+    ///
+    /// Every call reserves a slot, so concurrent callers are spaced too.
+    ///
+    /// # Arguments
+    ///
+    /// * chat_id - ID of a chat
+    pub async fn wait(&self, chat_id: Integer) {
+        let interval = self.get_interval(chat_id).await;
+        let send_at = self.reserve(chat_id, interval);
+        delay_until(send_at.into()).await;
+    }
+
+    /// Waits until a message can be sent to a chat and sends it
+    ///
+    /// # Arguments
+    ///
+    /// * chat_id - ID of a chat
+    /// * build - Returns a method which sends a message to given chat
+    pub async fn execute<F, M>(&self, chat_id: Integer, build: F) -> Result<Message, ExecuteError>
+    where
+        F: FnOnce(Integer) -> M,
+        M: Method<Response = Message>,
+    {
+        self.wait(chat_id).await;
+        self.api.execute(build(chat_id)).await
+    }
+
+    async fn get_interval(&self, chat_id: Integer) -> Duration {
+        let delay = match self.get_cached(chat_id) {
+            Some(delay) => delay,
+            None => self.fetch_delay(chat_id).await,
+        };
+        delay.unwrap_or(self.default_interval)
+    }
+
+    async fn fetch_delay(&self, chat_id: Integer) -> Option<Duration> {
+        let lock = self.get_fetch_lock(chat_id);
+        let _guard = lock.lock().await;
+        // a delay could be fetched while waiting for the lock
+        if let Some(delay) = self.get_cached(chat_id) {
+            return delay;
+        }
+        match self.api.execute(GetChat::new(chat_id)).await {
+            Ok(chat) => {
+                let delay = get_slow_mode_delay(&chat);
+                self.set_cached(chat_id, delay);
+                delay
+            }
+            Err(err) => {
+                log::warn!("Failed to get slow mode delay of chat {}: {}", chat_id, err);
+                None
+            }
+        }
+    }
+
+    fn get_fetch_lock(&self, chat_id: Integer) -> Arc<Mutex<()>> {
+        let mut state = self.state.lock().expect("Slow mode limiter lock poisoned");
+        if let Some(lock) = state.fetch_locks.get(&chat_id).and_then(Weak::upgrade) {
+            return lock;
+        }
+        // only locks of running requests are kept
+        state.fetch_locks.retain(|_, lock| lock.strong_count() > 0);
+        let lock = Arc::new(Mutex::new(()));
+        state.fetch_locks.insert(chat_id, Arc::downgrade(&lock));
+        lock
+    }
+
+    fn get_cached(&self, chat_id: Integer) -> Option<Option<Duration>> {
+        let state = self.state.lock().expect("Slow mode limiter lock poisoned");
+        state.delays.get(&chat_id, self.ttl).copied()
+    }
+
+    fn set_cached(&self, chat_id: Integer, delay: Option<Duration>) {
+        let mut state = self.state.lock().expect("Slow mode limiter lock poisoned");
+        state.delays.insert(chat_id, delay, self.capacity, self.ttl);
+    }
+
+    fn reserve(&self, chat_id: Integer, interval: Duration) -> Instant {
+        let mut state = self.state.lock().expect("Slow mode limiter lock poisoned");
+        state.schedule.reserve(chat_id, interval, self.capacity)
+    }
+}
+
+#[derive(Default)]
+struct SendSchedule {
+    // chat_id => time when next message can be sent
+    next_sends: HashMap<Integer, Instant>,
+    // (time when next message can be sent, chat_id)
+    order: BTreeSet<(Instant, Integer)>,
+}
+
+impl SendSchedule {
+    fn reserve(&mut self, chat_id: Integer, interval: Duration, capacity: usize) -> Instant {
+        let now = Instant::now();
+        // chats which can be sent to right now do not need a record
+        while let Some(&(next_send, _)) = self.order.iter().next() {
+            if next_send > now {
+                break;
+            }
+            self.remove_first();
+        }
+        let send_at = match self.next_sends.remove(&chat_id) {
+            Some(next_send) => {
+                self.order.remove(&(next_send, chat_id));
+                next_send
+            }
+            None => now,
+        };
+        let next_send = send_at + interval;
+        self.next_sends.insert(chat_id, next_send);
+        self.order.insert((next_send, chat_id));
+        while self.next_sends.len() > capacity {
+            self.remove_first();
+        }
+        send_at
+    }
+
+    fn remove_first(&mut self) {
+        if let Some(first) = self.order.iter().next().copied() {
+            self.order.remove(&first);
+            self.next_sends.remove(&first.1);
+        }
+    }
+}
+
+fn get_slow_mode_delay(chat: &Chat) -> Option<Duration> {
+    match chat {
+        Chat::Supergroup(chat) => chat
+            .slow_mode_delay
+            .filter(|delay| *delay > 0)
+            .map(|delay| Duration::from_secs(delay as u64)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{error_response, ok_response, MockServer};
+    use serde_json::json;
+    use tgbot::methods::SendMessage;
+
+    fn create_server() -> MockServer {
+        MockServer::new(|method, body| match (method, body["chat_id"].as_i64()) {
+            ("getChat", Some(-1)) => ok_response(json!({
+                "id": -1,
+                "type": "supergroup",
+                "title": "slow",
+                "slow_mode_delay": 30
+            })),
+            ("getChat", Some(-2)) => ok_response(json!({"id": -2, "type": "supergroup", "title": "fast"})),
+            ("getChat", Some(1)) => ok_response(json!({"id": 1, "type": "private", "first_name": "test"})),
+            ("sendMessage", Some(chat_id)) => ok_response(json!({
+                "message_id": 1,
+                "date": 0,
+                "from": {"id": 2, "is_bot": true, "first_name": "bot"},
+                "chat": {"id": chat_id, "type": "private", "first_name": "test"},
+                "text": body["text"]
+            })),
+            _ => error_response(),
+        })
+    }
+
+    #[tokio::test]
+    async fn interval() {
+        let server = create_server();
+        let limiter = SlowModeLimiter::new(server.api()).default_interval(Duration::from_millis(500));
+        assert_eq!(limiter.get_interval(-1).await, Duration::from_secs(30));
+        // cached
+        assert_eq!(limiter.get_interval(-1).await, Duration::from_secs(30));
+        // slow mode is disabled
+        assert_eq!(limiter.get_interval(-2).await, Duration::from_millis(500));
+        assert_eq!(limiter.get_interval(1).await, Duration::from_millis(500));
+        // request has failed
+        assert_eq!(limiter.get_interval(3).await, Duration::from_millis(500));
+        assert_eq!(
+            server
+                .requests()
+                .into_iter()
+                .map(|request| (request.method, request.body))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("getChat"), json!({"chat_id": -1})),
+                (String::from("getChat"), json!({"chat_id": -2})),
+                (String::from("getChat"), json!({"chat_id": 1})),
+                (String::from("getChat"), json!({"chat_id": 3})),
+            ]
+        );
+
+        let limiter = limiter.ttl(Duration::from_secs(0));
+        assert_eq!(limiter.get_interval(-1).await, Duration::from_secs(30));
+        assert_eq!(server.requests().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn concurrent_fetch() {
+        let server = create_server();
+        let limiter = SlowModeLimiter::new(server.api());
+        let (first, second) = tokio::join!(limiter.get_interval(-1), limiter.get_interval(-1));
+        assert_eq!(first, Duration::from_secs(30));
+        assert_eq!(second, Duration::from_secs(30));
+        assert_eq!(server.methods(), vec!["getChat"]);
+        assert!(limiter
+            .state
+            .lock()
+            .unwrap()
+            .fetch_locks
+            .values()
+            .all(|lock| lock.strong_count() == 0));
+    }
+
+    #[tokio::test]
+    async fn reserve() {
+        let server = MockServer::with_error();
+        let limiter = SlowModeLimiter::new(server.api());
+        let interval = Duration::from_secs(30);
+        let first = limiter.reserve(-1, interval);
+        assert!(first <= Instant::now());
+        assert_eq!(limiter.reserve(-1, interval), first + interval);
+        assert_eq!(limiter.reserve(-1, interval), first + interval * 2);
+        // another chat is not affected
+        assert!(limiter.reserve(-2, interval) <= Instant::now());
+
+        // chat which can receive a message soonest is forgotten
+        let limiter = limiter.capacity(2);
+        limiter.reserve(-3, interval * 10);
+        assert_eq!(limiter.state.lock().unwrap().schedule.next_sends.len(), 2);
+        assert!(limiter.reserve(-2, interval) <= Instant::now());
+        assert!(limiter.reserve(-3, interval) > Instant::now());
+    }
+
+    #[tokio::test]
+    async fn execute() {
+        let server = create_server();
+        let limiter = SlowModeLimiter::new(server.api()).default_interval(Duration::from_millis(100));
+        let started_at = Instant::now();
+        for text in &["first", "second"] {
+            limiter
+                .execute(1, |chat_id| SendMessage::new(chat_id, *text))
+                .await
+                .unwrap();
+        }
+        assert!(started_at.elapsed() >= Duration::from_millis(100));
+        assert_eq!(
+            server
+                .requests()
+                .into_iter()
+                .map(|request| (request.method, request.body))
+                .collect::<Vec<_>>(),
+            vec![
+                (String::from("getChat"), json!({"chat_id": 1})),
+                (String::from("sendMessage"), json!({"chat_id": 1, "text": "first"})),
+                (String::from("sendMessage"), json!({"chat_id": 1, "text": "second"})),
+            ]
+        );
+    }
+}
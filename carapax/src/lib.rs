@@ -4,6 +4,9 @@
 
 mod core;
 
+#[cfg(test)]
+mod test_utils;
+
 pub use self::core::*;
 pub use async_trait::async_trait;
 pub use tgbot::{
@@ -74,6 +77,16 @@ pub mod dialogue;
 #[cfg_attr(nightly, doc(cfg(feature = "i18n")))]
 pub mod i18n;
 
+/// Paginated messages with state stored in a session
+#[cfg(feature = "pagination")]
+#[cfg_attr(nightly, doc(cfg(feature = "pagination")))]
+pub mod pagination;
+
+/// Quiz with scores stored in a session
+#[cfg(feature = "quiz")]
+#[cfg_attr(nightly, doc(cfg(feature = "quiz")))]
+pub mod quiz;
+
 /// Ratelimit handler
 #[cfg(feature = "ratelimit")]
 #[cfg_attr(nightly, doc(cfg(feature = "ratelimit")))]
@@ -83,3 +96,8 @@ pub mod ratelimit;
 #[cfg(feature = "session")]
 #[cfg_attr(nightly, doc(cfg(feature = "session")))]
 pub mod session;
+
+/// Web App utilities
+#[cfg(feature = "webapp")]
+#[cfg_attr(nightly, doc(cfg(feature = "webapp")))]
+pub mod webapp;
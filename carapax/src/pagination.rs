@@ -0,0 +1,523 @@
+use crate::session::{SessionError, SessionManager};
+use async_trait::async_trait;
+use seance::backend::SessionBackend;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{error::Error, fmt};
+use tgbot::{
+    methods::{EditMessageText, SendMessage},
+    types::{CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Integer, Message},
+    Api, ExecuteError,
+};
+
+const SESSION_ID_PREFIX: &str = "__carapax_pagination";
+const CALLBACK_DATA_PREFIX: &str = "pg";
+const MAX_NAME_LEN: usize = 32;
+const DEFAULT_LIFETIME: u64 = 86400;
+
+/// A source of pages
+#[async_trait]
+pub trait PageSource {
+    /// Parameters of a query, stored in a session between callbacks
+    type Query: Serialize + DeserializeOwned + Send + Sync;
+
+    /// An error when getting a page
+    type Error: Error + Send + Sync + 'static;
+
+    /// Returns a page of items
+    ///
+    /// # Arguments
+    ///
+    /// * query - Query parameters
+    /// * offset - Number of items to skip
+    /// * limit - Max number of items on a page
+    async fn get_page(&self, query: &Self::Query, offset: usize, limit: usize) -> Result<Page, Self::Error>;
+}
+
+/// A page of items
+#[derive(Clone, Debug)]
+pub struct Page {
+    text: String,
+    has_next: bool,
+}
+
+impl Page {
+    /// Creates a new page
+    ///
+    /// # Arguments
+    ///
+    /// * text - Text of a message
+    /// * has_next - Whether there are more items after this page
+    pub fn new<T: Into<String>>(text: T, has_next: bool) -> Self {
+        Self {
+            text: text.into(),
+            has_next,
+        }
+    }
+}
+
+/// Sends paginated results with prev/next buttons
+///
+/// Query and current offset are stored in a session keyed by chat and message ID,
+/// so pages can be flipped using [handle_callback()](#method.handle_callback).
+/// State expires after [lifetime](#method.lifetime), so sessions do not grow forever.
+/// Callback data looks like `pg:{name}:{offset}` and fits into 64 bytes limit.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{
+///     async_trait,
+///     pagination::{Page, PageSource, Paginator, PaginatorError},
+///     session::{backend::SessionBackend, SessionManager},
+///     types::CallbackQuery,
+///     Api,
+/// };
+/// use std::convert::Infallible;
+///
+/// struct Numbers;
+///
+/// #[async_trait]
+/// impl PageSource for Numbers {
+///     type Query = u32;
+///     type Error = Infallible;
+///
+///     async fn get_page(&self, max: &u32, offset: usize, limit: usize) -> Result<Page, Self::Error> {
+///         let items: Vec<String> = (1..=*max).skip(offset).take(limit).map(|x| x.to_string()).collect();
+///         Ok(Page::new(items.join("\n"), offset + limit < *max as usize))
+///     }
+/// }
+///
+/// async fn handle<B>(api: &Api, session_manager: SessionManager<B>, query: CallbackQuery) -> Result<(), PaginatorError>
+/// where
+///     B: SessionBackend + Send + Sync,
+/// {
+///     let paginator = Paginator::new(session_manager, "numbers", Numbers, 10);
+///     if !paginator.handle_callback(api, &query).await? {
+///         // callback query is not related to the paginator
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct Paginator<B, S> {
+    session_manager: SessionManager<B>,
+    name: String,
+    source: S,
+    page_size: usize,
+    lifetime: u64,
+    prev_text: String,
+    next_text: String,
+}
+
+impl<B, S> Paginator<B, S>
+where
+    B: SessionBackend + Send + Sync,
+    S: PageSource + Send + Sync,
+{
+    /// Creates a new paginator
+    ///
+    /// # Arguments
+    ///
+    /// * session_manager - Session manager to store state
+    /// * name - Unique name used in callback data, up to 32 bytes
+    /// * source - Source of pages
+    /// * page_size - Max number of items on a page
+    ///
+    /// # Panics
+    ///
+    /// Panics if name is longer than 32 bytes or contains `:`, or if page_size is 0
+    pub fn new<N: Into<String>>(session_manager: SessionManager<B>, name: N, source: S, page_size: usize) -> Self {
+        let name = name.into();
+        assert!(
+            name.len() <= MAX_NAME_LEN && !name.contains(':'),
+            "Invalid paginator name: {}",
+            name
+        );
+        assert!(page_size > 0, "Invalid paginator page size: {}", page_size);
+        Self {
+            session_manager,
+            name,
+            source,
+            page_size,
+            lifetime: DEFAULT_LIFETIME,
+            prev_text: String::from("« Prev"),
+            next_text: String::from("Next »"),
+        }
+    }
+
+    /// Sets how long state of a message is kept in seconds
+    ///
+    /// Buttons of a message stop working when its state is expired.
+    /// Lifetime is extended every time a page is flipped.
+    /// Default value is 1 day
+    pub fn lifetime(mut self, seconds: u64) -> Self {
+        self.lifetime = seconds;
+        self
+    }
+
+    /// Sets text of prev/next buttons
+    pub fn button_texts<P: Into<String>, N: Into<String>>(mut self, prev: P, next: N) -> Self {
+        self.prev_text = prev.into();
+        self.next_text = next.into();
+        self
+    }
+
+    /// Sends a first page to a chat
+    pub async fn send<C: Into<ChatId>>(
+        &self,
+        api: &Api,
+        chat_id: C,
+        query: S::Query,
+    ) -> Result<Message, PaginatorError> {
+        let page = self.get_page(&query, 0).await?;
+        let mut method = SendMessage::new(chat_id, page.text.clone());
+        if let Some(markup) = self.create_markup(0, &page) {
+            method = method.reply_markup(markup);
+        }
+        let message = api.execute(method).await?;
+        let state = PaginatorState { query, offset: 0 };
+        self.set_state(message.get_chat_id(), message.id, &state).await?;
+        Ok(message)
+    }
+
+    /// Flips a page when a prev/next button is pressed
+    ///
+    /// Returns false if callback query is not related to the paginator
+    /// or state of the message was not found.
+    /// Note that the query is not answered.
+    pub async fn handle_callback(&self, api: &Api, query: &CallbackQuery) -> Result<bool, PaginatorError> {
+        let (message, offset) = match (&query.message, query.data.as_ref().and_then(|x| self.parse_data(x))) {
+            (Some(message), Some(offset)) => (message, offset),
+            _ => return Ok(false),
+        };
+        let chat_id = message.get_chat_id();
+        let mut state = match self.get_state(chat_id, message.id).await? {
+            Some(state) => state,
+            None => return Ok(false),
+        };
+        if state.offset == offset {
+            return Ok(true);
+        }
+        let page = self.get_page(&state.query, offset).await?;
+        let mut method = EditMessageText::new(chat_id, message.id, page.text.clone());
+        if let Some(markup) = self.create_markup(offset, &page) {
+            method = method.reply_markup(markup);
+        }
+        api.execute(method).await?;
+        state.offset = offset;
+        self.set_state(chat_id, message.id, &state).await?;
+        Ok(true)
+    }
+
+    async fn get_page(&self, query: &S::Query, offset: usize) -> Result<Page, PaginatorError> {
+        self.source
+            .get_page(query, offset, self.page_size)
+            .await
+            .map_err(|err| PaginatorError::Source(Box::new(err)))
+    }
+
+    fn create_markup(&self, offset: usize, page: &Page) -> Option<InlineKeyboardMarkup> {
+        let mut row = Vec::new();
+        if offset > 0 {
+            let prev_offset = offset.saturating_sub(self.page_size);
+            row.push(InlineKeyboardButton::with_callback_data(
+                self.prev_text.clone(),
+                self.create_data(prev_offset),
+            ));
+        }
+        if page.has_next {
+            row.push(InlineKeyboardButton::with_callback_data(
+                self.next_text.clone(),
+                self.create_data(offset + self.page_size),
+            ));
+        }
+        if row.is_empty() {
+            None
+        } else {
+            Some(InlineKeyboardMarkup::from_vec(vec![row]))
+        }
+    }
+
+    fn create_data(&self, offset: usize) -> String {
+        format!("{}:{}:{}", CALLBACK_DATA_PREFIX, self.name, offset)
+    }
+
+    fn parse_data(&self, data: &str) -> Option<usize> {
+        let mut parts = data.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(CALLBACK_DATA_PREFIX), Some(name), Some(offset)) if name == self.name => offset.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn get_session_id(&self, chat_id: Integer) -> String {
+        format!("{}:{}:{}", SESSION_ID_PREFIX, self.name, chat_id)
+    }
+
+    async fn get_state(
+        &self,
+        chat_id: Integer,
+        message_id: Integer,
+    ) -> Result<Option<PaginatorState<S::Query>>, PaginatorError> {
+        let mut session = self.session_manager.get_session_by_raw_id(self.get_session_id(chat_id));
+        Ok(session.get(message_id.to_string()).await?)
+    }
+
+    async fn set_state(
+        &self,
+        chat_id: Integer,
+        message_id: Integer,
+        state: &PaginatorState<S::Query>,
+    ) -> Result<(), PaginatorError> {
+        let mut session = self.session_manager.get_session_by_raw_id(self.get_session_id(chat_id));
+        let key = message_id.to_string();
+        session.set(&key, state).await?;
+        session.expire(&key, self.lifetime).await?;
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct PaginatorState<Q> {
+    query: Q,
+    offset: usize,
+}
+
+/// An error occurred in paginator
+#[derive(Debug)]
+pub enum PaginatorError {
+    /// Failed to send or edit a message
+    Execute(ExecuteError),
+    /// Failed to read or write state
+    Session(SessionError),
+    /// Failed to get a page
+    Source(Box<dyn Error + Send + Sync>),
+}
+
+impl From<ExecuteError> for PaginatorError {
+    fn from(err: ExecuteError) -> Self {
+        PaginatorError::Execute(err)
+    }
+}
+
+impl From<SessionError> for PaginatorError {
+    fn from(err: SessionError) -> Self {
+        PaginatorError::Session(err)
+    }
+}
+
+impl Error for PaginatorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PaginatorError::Execute(err) => Some(err),
+            PaginatorError::Session(err) => Some(err),
+            PaginatorError::Source(err) => Some(err.as_ref()),
+        }
+    }
+}
+
+impl fmt::Display for PaginatorError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PaginatorError::Execute(err) => write!(out, "failed to send a page: {}", err),
+            PaginatorError::Session(err) => write!(out, "failed to access paginator state: {}", err),
+            PaginatorError::Source(err) => write!(out, "failed to get a page: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::backend::MemoryBackend;
+    use crate::test_utils::{error_response, ok_response, MockServer};
+    use serde_json::json;
+    use std::{
+        convert::Infallible,
+        sync::atomic::{AtomicBool, Ordering},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    struct Numbers;
+
+    #[async_trait]
+    impl PageSource for Numbers {
+        type Query = usize;
+        type Error = Infallible;
+
+        async fn get_page(&self, max: &usize, offset: usize, limit: usize) -> Result<Page, Self::Error> {
+            let items: Vec<String> = (1..=*max).skip(offset).take(limit).map(|x| x.to_string()).collect();
+            Ok(Page::new(items.join(","), offset + limit < *max))
+        }
+    }
+
+    fn create_paginator() -> Paginator<MemoryBackend, Numbers> {
+        Paginator::new(SessionManager::new(MemoryBackend::new(10)), "numbers", Numbers, 2)
+    }
+
+    fn create_query(data: &str) -> CallbackQuery {
+        serde_json::from_value(serde_json::json!({
+            "id": "query-id",
+            "from": {"id": 1, "is_bot": false, "first_name": "test"},
+            "message": {
+                "message_id": 2,
+                "date": 0,
+                "from": {"id": 2, "is_bot": true, "first_name": "bot"},
+                "chat": {"id": 1, "type": "private", "first_name": "test"},
+                "text": "1,2"
+            },
+            "data": data
+        }))
+        .unwrap()
+    }
+
+    fn get_callback_data(markup: Option<InlineKeyboardMarkup>) -> Vec<String> {
+        markup
+            .map(|markup| markup.into_vec().into_iter().flatten().collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|button: InlineKeyboardButton| serde_json::to_value(button).unwrap()["callback_data"].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn markup() {
+        let paginator = create_paginator();
+        assert_eq!(
+            get_callback_data(paginator.create_markup(0, &Page::new("1,2", true))),
+            vec![r#""pg:numbers:2""#]
+        );
+        assert_eq!(
+            get_callback_data(paginator.create_markup(2, &Page::new("3,4", true))),
+            vec![r#""pg:numbers:0""#, r#""pg:numbers:4""#]
+        );
+        assert!(get_callback_data(paginator.create_markup(0, &Page::new("1", false))).is_empty());
+
+        assert_eq!(paginator.parse_data("pg:numbers:4"), Some(4));
+        assert_eq!(paginator.parse_data("pg:other:4"), None);
+        assert_eq!(paginator.parse_data("pg:numbers:x"), None);
+        assert_eq!(paginator.parse_data("data"), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_name() {
+        Paginator::new(SessionManager::new(MemoryBackend::new(10)), "a:b", Numbers, 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_page_size() {
+        Paginator::new(SessionManager::new(MemoryBackend::new(10)), "numbers", Numbers, 0);
+    }
+
+    #[tokio::test]
+    async fn state_lifetime() {
+        let mut backend = MemoryBackend::new(10);
+        let paginator = Paginator::new(SessionManager::new(backend.clone()), "numbers", Numbers, 2).lifetime(60);
+        let state = PaginatorState { query: 5, offset: 0 };
+        paginator.set_state(1, 2, &state).await.unwrap();
+        let data = backend
+            .read_value(&paginator.get_session_id(1), "2")
+            .await
+            .unwrap()
+            .unwrap();
+        let expires_at = serde_json::from_slice::<serde_json::Value>(&data).unwrap()["expires_at"]
+            .as_u64()
+            .unwrap();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert!(expires_at > now && expires_at <= now + 60);
+    }
+
+    #[tokio::test]
+    async fn send() {
+        let server = MockServer::new(|_, _| {
+            ok_response(json!({
+                "message_id": 2,
+                "date": 0,
+                "from": {"id": 2, "is_bot": true, "first_name": "bot"},
+                "chat": {"id": 1, "type": "private", "first_name": "test"},
+                "text": "1,2"
+            }))
+        });
+        let paginator = create_paginator();
+        let message = paginator.send(&server.api(), 1, 5).await.unwrap();
+        assert_eq!(message.id, 2);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "sendMessage");
+        assert_eq!(
+            requests[0].body,
+            json!({
+                "chat_id": 1,
+                "text": "1,2",
+                "reply_markup": {"inline_keyboard": [[{"text": "Next »", "callback_data": "pg:numbers:2"}]]}
+            })
+        );
+        let state = paginator.get_state(1, 2).await.unwrap().unwrap();
+        assert_eq!((state.query, state.offset), (5, 0));
+    }
+
+    #[tokio::test]
+    async fn handle_callback() {
+        // first request fails
+        let failed = AtomicBool::new(false);
+        let server = MockServer::new(move |_, _| {
+            if failed.swap(true, Ordering::SeqCst) {
+                ok_response(json!(true))
+            } else {
+                error_response()
+            }
+        });
+        let api = server.api();
+        let paginator = create_paginator();
+
+        // unknown message
+        assert!(!paginator
+            .handle_callback(&api, &create_query("pg:numbers:2"))
+            .await
+            .unwrap());
+        // unknown data
+        assert!(!paginator.handle_callback(&api, &create_query("data")).await.unwrap());
+
+        let state = PaginatorState { query: 5, offset: 0 };
+        paginator.set_state(1, 2, &state).await.unwrap();
+        // same page
+        assert!(paginator
+            .handle_callback(&api, &create_query("pg:numbers:0"))
+            .await
+            .unwrap());
+        assert!(server.requests().is_empty());
+
+        // page is requested
+        match paginator.handle_callback(&api, &create_query("pg:numbers:2")).await {
+            Err(PaginatorError::Execute(_)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        // offset is not changed when request failed
+        assert_eq!(paginator.get_state(1, 2).await.unwrap().unwrap().offset, 0);
+
+        assert!(paginator
+            .handle_callback(&api, &create_query("pg:numbers:2"))
+            .await
+            .unwrap());
+        assert_eq!(paginator.get_state(1, 2).await.unwrap().unwrap().offset, 2);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        for request in requests {
+            assert_eq!(request.method, "editMessageText");
+            assert_eq!(
+                request.body,
+                json!({
+                    "chat_id": 1,
+                    "message_id": 2,
+                    "text": "3,4",
+                    "reply_markup": {"inline_keyboard": [[
+                        {"text": "« Prev", "callback_data": "pg:numbers:0"},
+                        {"text": "Next »", "callback_data": "pg:numbers:4"}
+                    ]]}
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,626 @@
+use crate::session::{Session, SessionError, SessionManager};
+use seance::backend::SessionBackend;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fmt,
+};
+use tgbot::{
+    methods::SendQuiz,
+    types::{ChatId, Integer, Message, MessageData, Poll, PollAnswer},
+    Api, ExecuteError,
+};
+
+const SESSION_ID_PREFIX: &str = "__carapax_quiz";
+const SESSION_KEY: &str = "state";
+
+/// A series of quiz questions with scores stored in a session
+///
+/// Questions are sent one by one using [next_question()](#method.next_question).
+/// Answers received in `PollAnswer` updates are passed to [record_answer()](#method.record_answer).
+/// Note that Telegram sends `PollAnswer` updates only for non-anonymous polls,
+/// so all questions are sent with `is_anonymous=false`.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{
+///     quiz::{Quiz, QuizError, QuizQuestion},
+///     session::{backend::SessionBackend, SessionManager},
+///     types::PollAnswer,
+/// };
+///
+/// fn create_quiz<B: SessionBackend>(session_manager: SessionManager<B>) -> Quiz<B> {
+///     Quiz::new(
+///         session_manager,
+///         "capitals",
+///         vec![
+///             QuizQuestion::new("Capital of France?", vec!["Paris", "Rome"], 0),
+///             QuizQuestion::new("Capital of Italy?", vec!["Paris", "Rome"], 1),
+///         ],
+///     )
+/// }
+///
+/// async fn handle_answer<B: SessionBackend>(quiz: &Quiz<B>, answer: PollAnswer) -> Result<(), QuizError> {
+///     if let Some(is_correct) = quiz.record_answer(&answer).await? {
+///         println!("Answer is correct: {}", is_correct);
+///     }
+///     for score in quiz.leaderboard().await? {
+///         println!("{}: {}", score.name, score.correct);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct Quiz<B> {
+    session_manager: SessionManager<B>,
+    session_id: String,
+    questions: Vec<QuizQuestion>,
+}
+
+impl<B> Quiz<B>
+where
+    B: SessionBackend,
+{
+    /// Creates a new quiz
+    ///
+    /// # Arguments
+    ///
+    /// * session_manager - Session manager to store quiz state
+    /// * name - Unique name of the quiz, used as a session ID
+    /// * questions - List of questions
+    pub fn new<N>(session_manager: SessionManager<B>, name: N, questions: Vec<QuizQuestion>) -> Self
+    where
+        N: fmt::Display,
+    {
+        Self {
+            session_manager,
+            session_id: format!("{}:{}", SESSION_ID_PREFIX, name),
+            questions,
+        }
+    }
+
+    fn get_session(&self) -> Session<B> {
+        self.session_manager.get_session_by_raw_id(&self.session_id)
+    }
+
+    async fn load_state(&self, session: &mut Session<B>) -> Result<QuizState, QuizError> {
+        Ok(session.get(SESSION_KEY).await?.unwrap_or_default())
+    }
+
+    /// Sends a next question to a chat
+    ///
+    /// Returns None when all questions have been sent.
+    /// A question is reserved before it is sent, so concurrent calls send different questions.
+    /// When sending fails, the question is released and sent by a next call.
+    /// State is updated using [SessionManager::update()](../session/struct.SessionManager.html#method.update),
+    /// so answers recorded while a question is being sent are not lost.
+    pub async fn next_question<C>(&self, api: &Api, chat_id: C) -> Result<Option<Message>, QuizError>
+    where
+        C: Into<ChatId>,
+    {
+        let total = self.questions.len();
+        let mut idx = None;
+        self.session_manager
+            .update(&self.session_id, SESSION_KEY, |state: Option<QuizState>| {
+                let mut state = state.unwrap_or_default();
+                idx = state.reserve_question(total);
+                state
+            })
+            .await?;
+        let idx = match idx {
+            Some(idx) => idx,
+            None => return Ok(None),
+        };
+        let question = &self.questions[idx];
+        let (message, poll_id) = match send_question(api, chat_id, question).await {
+            Ok(result) => result,
+            Err(err) => {
+                let released = self
+                    .session_manager
+                    .update(&self.session_id, SESSION_KEY, |state: Option<QuizState>| {
+                        let mut state = state.unwrap_or_default();
+                        state.release_question(idx);
+                        state
+                    })
+                    .await;
+                if let Err(release_err) = released {
+                    log::error!("Failed to release quiz question {}: {}", idx, release_err);
+                }
+                return Err(err);
+            }
+        };
+        let poll = QuizPoll {
+            question: idx,
+            correct_option_id: question.correct_option_id,
+        };
+        self.session_manager
+            .update(&self.session_id, SESSION_KEY, |state: Option<QuizState>| {
+                let mut state = state.unwrap_or_default();
+                state.polls.insert(poll_id, poll);
+                state
+            })
+            .await?;
+        Ok(Some(message))
+    }
+
+    /// Records an answer to a question
+    ///
+    /// Returns whether answer is correct or None
+    /// if answer does not belong to the quiz or was already recorded
+    pub async fn record_answer(&self, answer: &PollAnswer) -> Result<Option<bool>, QuizError> {
+        let mut result = None;
+        self.session_manager
+            .update(&self.session_id, SESSION_KEY, |state: Option<QuizState>| {
+                let mut state = state.unwrap_or_default();
+                result = state.record_answer(answer);
+                state
+            })
+            .await?;
+        Ok(result)
+    }
+
+    /// Returns scores of all users answered at least one question
+    ///
+    /// Sorted by number of correct answers in descending order
+    pub async fn leaderboard(&self) -> Result<Vec<QuizScore>, QuizError> {
+        let mut session = self.get_session();
+        let state = self.load_state(&mut session).await?;
+        let asked = state.polls.len();
+        let mut leaderboard: Vec<QuizScore> = state
+            .scores
+            .into_iter()
+            .map(|(user_id, score)| QuizScore {
+                user_id,
+                name: score.name,
+                correct: score.correct.len(),
+                answered: score.answered.len(),
+                skipped: asked.saturating_sub(score.answered.len()),
+            })
+            .collect();
+        leaderboard.sort_by(|a, b| {
+            b.correct
+                .cmp(&a.correct)
+                .then(a.skipped.cmp(&b.skipped))
+                .then(a.user_id.cmp(&b.user_id))
+        });
+        Ok(leaderboard)
+    }
+
+    /// Removes quiz state, so it can be started again
+    pub async fn reset(&self) -> Result<(), QuizError> {
+        let mut session = self.get_session();
+        session.remove(SESSION_KEY).await?;
+        Ok(())
+    }
+}
+
+async fn send_question<C>(api: &Api, chat_id: C, question: &QuizQuestion) -> Result<(Message, String), QuizError>
+where
+    C: Into<ChatId>,
+{
+    let mut method = SendQuiz::new(chat_id, question.question.clone())
+        .is_anonymous(false)
+        .correct_option_id(question.correct_option_id);
+    for option in &question.options {
+        method = method.option(option.clone());
+    }
+    if let Some(ref explanation) = question.explanation {
+        method = method.explanation(explanation.clone());
+    }
+    let message = api.execute(method).await?;
+    let poll_id = match message.data {
+        MessageData::Poll(Poll::Quiz(ref quiz)) => quiz.id.clone(),
+        _ => return Err(QuizError::PollNotFound),
+    };
+    Ok((message, poll_id))
+}
+
+/// A quiz question
+#[derive(Clone, Debug)]
+pub struct QuizQuestion {
+    question: String,
+    options: Vec<String>,
+    correct_option_id: Integer,
+    explanation: Option<String>,
+}
+
+impl QuizQuestion {
+    /// Creates a new question
+    ///
+    /// # Arguments
+    ///
+    /// * question - Question text, 1-255 characters
+    /// * options - Answer options, 2-10 strings 1-100 characters each
+    /// * correct_option_id - 0-based identifier of the correct answer option
+    pub fn new<Q, O>(question: Q, options: Vec<O>, correct_option_id: Integer) -> Self
+    where
+        Q: Into<String>,
+        O: Into<String>,
+    {
+        Self {
+            question: question.into(),
+            options: options.into_iter().map(Into::into).collect(),
+            correct_option_id,
+            explanation: None,
+        }
+    }
+
+    /// Text that is shown when a user chooses an incorrect answer
+    pub fn explanation<E: Into<String>>(mut self, explanation: E) -> Self {
+        self.explanation = Some(explanation.into());
+        self
+    }
+}
+
+/// Score of a user
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuizScore {
+    /// ID of the user
+    pub user_id: Integer,
+    /// First name of the user
+    pub name: String,
+    /// Number of correct answers
+    pub correct: usize,
+    /// Number of answered questions
+    pub answered: usize,
+    /// Number of sent questions which user did not answer
+    pub skipped: usize,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct QuizState {
+    polls: HashMap<String, QuizPoll>,
+    scores: HashMap<Integer, UserScore>,
+    // number of reserved questions
+    reserved: usize,
+    // reserved questions which have failed to send
+    released: BTreeSet<usize>,
+}
+
+impl QuizState {
+    fn reserve_question(&mut self, total: usize) -> Option<usize> {
+        if let Some(idx) = self.released.iter().next().copied() {
+            self.released.remove(&idx);
+            return Some(idx);
+        }
+        if self.reserved < total {
+            self.reserved += 1;
+            Some(self.reserved - 1)
+        } else {
+            None
+        }
+    }
+
+    fn release_question(&mut self, idx: usize) {
+        if idx + 1 == self.reserved {
+            self.reserved = idx;
+        } else {
+            self.released.insert(idx);
+        }
+    }
+
+    fn record_answer(&mut self, answer: &PollAnswer) -> Option<bool> {
+        let poll = self.polls.get(&answer.poll_id)?;
+        let option_id = *answer.option_ids.first()?;
+        let user = &answer.user;
+        let score = self.scores.entry(user.id).or_insert_with(|| UserScore {
+            name: user.first_name.clone(),
+            correct: Vec::new(),
+            answered: Vec::new(),
+        });
+        if score.answered.contains(&poll.question) {
+            return None;
+        }
+        score.answered.push(poll.question);
+        let is_correct = option_id == poll.correct_option_id;
+        if is_correct {
+            score.correct.push(poll.question);
+        }
+        Some(is_correct)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct QuizPoll {
+    question: usize,
+    correct_option_id: Integer,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct UserScore {
+    name: String,
+    correct: Vec<usize>,
+    answered: Vec<usize>,
+}
+
+/// An error occurred in quiz
+#[derive(Debug)]
+pub enum QuizError {
+    /// Failed to send a question
+    Execute(ExecuteError),
+    /// Sent message does not contain a quiz
+    PollNotFound,
+    /// Failed to read or write quiz state
+    Session(SessionError),
+}
+
+impl From<ExecuteError> for QuizError {
+    fn from(err: ExecuteError) -> Self {
+        QuizError::Execute(err)
+    }
+}
+
+impl From<SessionError> for QuizError {
+    fn from(err: SessionError) -> Self {
+        QuizError::Session(err)
+    }
+}
+
+impl Error for QuizError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            QuizError::Execute(err) => Some(err),
+            QuizError::PollNotFound => None,
+            QuizError::Session(err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for QuizError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuizError::Execute(err) => write!(out, "failed to send a question: {}", err),
+            QuizError::PollNotFound => write!(out, "sent message does not contain a quiz"),
+            QuizError::Session(err) => write!(out, "failed to access quiz state: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        session::backend::MemoryBackend,
+        test_utils::{error_response, ok_response, MockServer},
+    };
+    use serde_json::{json, Value};
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+    use tokio::sync::oneshot;
+
+    fn create_quiz() -> Quiz<MemoryBackend> {
+        Quiz::new(
+            SessionManager::new(MemoryBackend::new(10)),
+            "test",
+            vec![
+                QuizQuestion::new("q1", vec!["a", "b"], 0),
+                QuizQuestion::new("q2", vec!["a", "b"], 1).explanation("e2"),
+            ],
+        )
+    }
+
+    fn create_poll_response(idx: usize, body: &Value) -> Value {
+        ok_response(json!({
+            "message_id": idx,
+            "date": 0,
+            "from": {"id": 2, "is_bot": true, "first_name": "bot"},
+            "chat": {"id": 1, "type": "private", "first_name": "test"},
+            "poll": {
+                "id": format!("poll{}", idx),
+                "question": body["question"],
+                "options": [{"text": "a", "voter_count": 0}, {"text": "b", "voter_count": 0}],
+                "is_closed": false,
+                "total_voter_count": 0,
+                "is_anonymous": false,
+                "type": "quiz",
+                "correct_option_id": body["correct_option_id"]
+            }
+        }))
+    }
+
+    fn get_questions(server: &MockServer) -> Vec<String> {
+        server
+            .requests()
+            .into_iter()
+            .map(|request| request.body["question"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn create_answer(poll_id: &str, user_id: Integer, option_id: Integer) -> PollAnswer {
+        serde_json::from_value(serde_json::json!({
+            "poll_id": poll_id,
+            "user": {"id": user_id, "is_bot": false, "first_name": format!("user{}", user_id)},
+            "option_ids": [option_id]
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn quiz() {
+        let quiz = create_quiz();
+
+        // emulate sent questions
+        let mut session = quiz.get_session();
+        let mut state = QuizState::default();
+        state.polls.insert(
+            String::from("poll1"),
+            QuizPoll {
+                question: 0,
+                correct_option_id: 0,
+            },
+        );
+        state.polls.insert(
+            String::from("poll2"),
+            QuizPoll {
+                question: 1,
+                correct_option_id: 1,
+            },
+        );
+        session.set(SESSION_KEY, &state).await.unwrap();
+
+        assert_eq!(
+            quiz.record_answer(&create_answer("poll1", 1, 0)).await.unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            quiz.record_answer(&create_answer("poll2", 1, 1)).await.unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            quiz.record_answer(&create_answer("poll1", 2, 1)).await.unwrap(),
+            Some(false)
+        );
+        assert_eq!(
+            quiz.record_answer(&create_answer("poll2", 3, 1)).await.unwrap(),
+            Some(true)
+        );
+        // repeated answer
+        assert_eq!(quiz.record_answer(&create_answer("poll2", 3, 1)).await.unwrap(), None);
+        // unknown poll
+        assert_eq!(quiz.record_answer(&create_answer("poll3", 3, 1)).await.unwrap(), None);
+
+        let leaderboard = quiz.leaderboard().await.unwrap();
+        assert_eq!(
+            leaderboard,
+            vec![
+                QuizScore {
+                    user_id: 1,
+                    name: String::from("user1"),
+                    correct: 2,
+                    answered: 2,
+                    skipped: 0,
+                },
+                QuizScore {
+                    user_id: 3,
+                    name: String::from("user3"),
+                    correct: 1,
+                    answered: 1,
+                    skipped: 1,
+                },
+                QuizScore {
+                    user_id: 2,
+                    name: String::from("user2"),
+                    correct: 0,
+                    answered: 1,
+                    skipped: 1,
+                },
+            ]
+        );
+
+        quiz.reset().await.unwrap();
+        assert!(quiz.leaderboard().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn next_question() {
+        let quiz = create_quiz();
+
+        // second response is held until an answer is recorded
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(Some(release_rx)));
+        let counter = Arc::new(AtomicUsize::new(0));
+        let mut server = MockServer::with_async(move |_, body| {
+            let (release_rx, counter) = (release_rx.clone(), counter.clone());
+            async move {
+                let idx = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                if idx == 2 {
+                    let release_rx = release_rx.lock().unwrap().take().unwrap();
+                    release_rx.await.unwrap();
+                }
+                create_poll_response(idx, &body)
+            }
+        });
+        let api = server.api();
+
+        let message = quiz.next_question(&api, 1).await.unwrap().unwrap();
+        assert_eq!(message.id, 1);
+        let (message, ()) = tokio::join!(quiz.next_question(&api, 1), async {
+            server.wait_requests(2).await;
+            assert_eq!(
+                quiz.record_answer(&create_answer("poll1", 1, 0)).await.unwrap(),
+                Some(true)
+            );
+            release_tx.send(()).unwrap();
+        });
+        assert_eq!(message.unwrap().unwrap().id, 2);
+        // all questions have been sent
+        assert!(quiz.next_question(&api, 1).await.unwrap().is_none());
+
+        assert_eq!(
+            quiz.record_answer(&create_answer("poll2", 1, 0)).await.unwrap(),
+            Some(false)
+        );
+        // answer recorded while the second question was being sent is kept
+        let leaderboard = quiz.leaderboard().await.unwrap();
+        assert_eq!(leaderboard.len(), 1);
+        assert_eq!((leaderboard[0].correct, leaderboard[0].answered), (1, 2));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|request| request.method == "sendPoll"));
+        assert_eq!(
+            requests[0].body,
+            json!({
+                "chat_id": 1,
+                "question": "q1",
+                "options": ["a", "b"],
+                "is_anonymous": false,
+                "type": "quiz",
+                "correct_option_id": 0
+            })
+        );
+        assert_eq!(requests[1].body["explanation"], "e2");
+    }
+
+    #[tokio::test]
+    async fn concurrent_questions() {
+        let quiz = create_quiz();
+        let counter = AtomicUsize::new(0);
+        let server = MockServer::new(move |_, body| {
+            let idx = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            create_poll_response(idx, body)
+        });
+        let api = server.api();
+        let (first, second) = tokio::join!(quiz.next_question(&api, 1), quiz.next_question(&api, 1));
+        assert!(first.unwrap().is_some());
+        assert!(second.unwrap().is_some());
+        assert!(quiz.next_question(&api, 1).await.unwrap().is_none());
+        let mut questions = get_questions(&server);
+        questions.sort();
+        assert_eq!(questions, vec!["q1", "q2"]);
+    }
+
+    #[tokio::test]
+    async fn failed_question() {
+        let quiz = create_quiz();
+        let failed = AtomicBool::new(false);
+        let counter = AtomicUsize::new(0);
+        let server = MockServer::new(move |_, body| {
+            if !failed.swap(true, Ordering::SeqCst) {
+                return error_response();
+            }
+            let idx = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            create_poll_response(idx, body)
+        });
+        let api = server.api();
+        assert!(matches!(quiz.next_question(&api, 1).await, Err(QuizError::Execute(_))));
+        // released question is sent again
+        assert_eq!(quiz.next_question(&api, 1).await.unwrap().unwrap().id, 1);
+        assert_eq!(quiz.next_question(&api, 1).await.unwrap().unwrap().id, 2);
+        assert!(quiz.next_question(&api, 1).await.unwrap().is_none());
+        assert_eq!(get_questions(&server), vec!["q1", "q1", "q2"]);
+
+        let mut state = QuizState::default();
+        assert_eq!(state.reserve_question(3), Some(0));
+        assert_eq!(state.reserve_question(3), Some(1));
+        state.release_question(0);
+        assert_eq!(state.reserve_question(3), Some(0));
+        assert_eq!(state.reserve_question(3), Some(2));
+        state.release_question(2);
+        assert_eq!(state.reserved, 2);
+        assert!(state.released.is_empty());
+    }
+}
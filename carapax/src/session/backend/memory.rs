@@ -0,0 +1,434 @@
+use async_trait::async_trait;
+use seance::backend::SessionBackend;
+use serde::{de::IgnoredAny, Deserialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, SystemTime, SystemTimeError},
+};
+use tokio::{task::JoinHandle, time::interval};
+
+/// In-memory backend
+///
+/// Keeps at most `capacity` values across all sessions.
+/// When the limit is reached, an expired value is evicted if any,
+/// otherwise least recently used value is evicted,
+/// so [Session::get()](../struct.Session.html#method.get) returns None for it.
+/// Expiration of values is handled by session as usual,
+/// expired values are kept until they are evicted or removed by a garbage collector,
+/// see [spawn_gc()](#method.spawn_gc).
+///
+/// Cloned backend shares the same storage,
+/// so you can pass a clone to [SessionCollector](../struct.SessionCollector.html).
+///
+/// Data is lost when process exits, use it as a best-effort cache only.
+#[derive(Clone)]
+pub struct MemoryBackend {
+    store: Arc<Mutex<MemoryStore>>,
+}
+
+impl MemoryBackend {
+    /// Creates a new backend
+    ///
+    /// # Arguments
+    ///
+    /// * capacity - Maximum number of values in all sessions
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            store: Arc::new(Mutex::new(MemoryStore {
+                capacity,
+                counter: 0,
+                sessions: HashMap::new(),
+                usage: BTreeMap::new(),
+                expiring: BTreeSet::new(),
+            })),
+        }
+    }
+
+    /// Removes expired values
+    ///
+    /// Returns number of removed values
+    pub fn collect_expired(&self) -> Result<usize, SystemTimeError> {
+        let timestamp = now()?;
+        Ok(self.with_store(|store| store.collect_expired(timestamp)))
+    }
+
+    /// Spawns a task which removes expired values periodically
+    ///
+    /// The task stops when all clones of the backend are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * period - Interval between runs
+    pub fn spawn_gc(&self, period: Duration) -> JoinHandle<()> {
+        let store = Arc::downgrade(&self.store);
+        tokio::spawn(run_gc(store, period))
+    }
+
+    fn with_store<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut MemoryStore) -> T,
+    {
+        let mut store = self.store.lock().expect("Memory backend lock poisoned");
+        f(&mut store)
+    }
+}
+
+async fn run_gc(store: Weak<Mutex<MemoryStore>>, period: Duration) {
+    let mut interval = interval(period);
+    loop {
+        interval.tick().await;
+        let store = match store.upgrade() {
+            Some(store) => store,
+            None => break,
+        };
+        let timestamp = match now() {
+            Ok(timestamp) => timestamp,
+            Err(err) => {
+                log::error!("Failed to collect expired session values: {}", err);
+                continue;
+            }
+        };
+        let mut store = store.lock().expect("Memory backend lock poisoned");
+        store.collect_expired(timestamp);
+    }
+}
+
+struct MemoryStore {
+    capacity: usize,
+    counter: u64,
+    sessions: HashMap<String, MemorySession>,
+    // usage counter => (session_id, key)
+    usage: BTreeMap<u64, (String, String)>,
+    // (expires_at, usage counter)
+    expiring: BTreeSet<(u64, u64)>,
+}
+
+struct MemorySession {
+    created_at: u64,
+    values: HashMap<String, MemoryValue>,
+}
+
+struct MemoryValue {
+    data: Vec<u8>,
+    used_at: u64,
+    expires_at: Option<u64>,
+}
+
+/// A value written by session
+///
+/// Session stores a deadline set by `expire()` along with a value: `{"expires_at": .., "value": ..}`.
+/// `SessionBackend` has no other way to pass a deadline, so it is read from stored data.
+#[derive(Deserialize)]
+struct StoredValue {
+    // both fields are required, so a changed format is not taken for a value without a deadline
+    #[serde(deserialize_with = "Option::deserialize")]
+    expires_at: Option<u64>,
+    #[serde(rename = "value")]
+    _value: IgnoredAny,
+}
+
+/// Returns a deadline of a value or None when data has an unknown format
+fn parse_expires_at(data: &[u8]) -> Option<Option<u64>> {
+    serde_json::from_slice::<StoredValue>(data)
+        .ok()
+        .map(|value| value.expires_at)
+}
+
+impl MemoryStore {
+    fn set(&mut self, session_id: &str, key: &str, data: Vec<u8>, timestamp: u64) {
+        let session = self
+            .sessions
+            .entry(String::from(session_id))
+            .or_insert_with(|| MemorySession {
+                created_at: timestamp,
+                values: HashMap::new(),
+            });
+        let expires_at = parse_expires_at(&data).unwrap_or_else(|| {
+            log::warn!(
+                "Unknown format of session value {}/{}, expiration is not tracked",
+                session_id,
+                key
+            );
+            None
+        });
+        let value = MemoryValue {
+            expires_at,
+            data,
+            used_at: 0,
+        };
+        if let Some(old_value) = session.values.insert(String::from(key), value) {
+            self.forget(&old_value);
+        }
+        self.touch(session_id, key);
+        self.evict(timestamp);
+    }
+
+    fn touch(&mut self, session_id: &str, key: &str) {
+        self.counter += 1;
+        let counter = self.counter;
+        if let Some(value) = self
+            .sessions
+            .get_mut(session_id)
+            .and_then(|session| session.values.get_mut(key))
+        {
+            self.usage.remove(&value.used_at);
+            self.usage
+                .insert(counter, (String::from(session_id), String::from(key)));
+            if let Some(expires_at) = value.expires_at {
+                self.expiring.remove(&(expires_at, value.used_at));
+                self.expiring.insert((expires_at, counter));
+            }
+            value.used_at = counter;
+        }
+    }
+
+    fn forget(&mut self, value: &MemoryValue) {
+        self.usage.remove(&value.used_at);
+        if let Some(expires_at) = value.expires_at {
+            self.expiring.remove(&(expires_at, value.used_at));
+        }
+    }
+
+    fn remove(&mut self, session_id: &str, key: &str) {
+        if let Some(session) = self.sessions.get_mut(session_id) {
+            let value = session.values.remove(key);
+            if session.values.is_empty() {
+                self.sessions.remove(session_id);
+            }
+            if let Some(value) = value {
+                self.forget(&value);
+            }
+        }
+    }
+
+    fn remove_session(&mut self, session_id: &str) {
+        if let Some(session) = self.sessions.remove(session_id) {
+            for value in session.values.values() {
+                self.forget(value);
+            }
+        }
+    }
+
+    fn remove_used_at(&mut self, used_at: u64) {
+        if let Some((session_id, key)) = self.usage.get(&used_at).cloned() {
+            self.remove(&session_id, &key);
+        }
+    }
+
+    fn get_first_expired(&self, timestamp: u64) -> Option<u64> {
+        match self.expiring.iter().next() {
+            Some(&(expires_at, used_at)) if expires_at < timestamp => Some(used_at),
+            _ => None,
+        }
+    }
+
+    fn collect_expired(&mut self, timestamp: u64) -> usize {
+        let mut count = 0;
+        while let Some(used_at) = self.get_first_expired(timestamp) {
+            self.remove_used_at(used_at);
+            count += 1;
+        }
+        count
+    }
+
+    fn evict(&mut self, timestamp: u64) {
+        while self.usage.len() > self.capacity {
+            // expired values are evicted before live ones
+            let used_at = match self.get_first_expired(timestamp) {
+                Some(used_at) => used_at,
+                None => match self.usage.keys().next() {
+                    Some(used_at) => *used_at,
+                    None => break,
+                },
+            };
+            self.remove_used_at(used_at);
+        }
+    }
+}
+
+fn now() -> Result<u64, SystemTimeError> {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|x| x.as_secs())
+}
+
+#[async_trait]
+impl SessionBackend for MemoryBackend {
+    type Error = SystemTimeError;
+
+    async fn get_sessions(&mut self) -> Result<Vec<String>, Self::Error> {
+        Ok(self.with_store(|store| store.sessions.keys().cloned().collect()))
+    }
+
+    async fn get_session_age(&mut self, session_id: &str) -> Result<Option<u64>, Self::Error> {
+        Ok(self.with_store(|store| store.sessions.get(session_id).map(|session| session.created_at)))
+    }
+
+    async fn remove_session(&mut self, session_id: &str) -> Result<(), Self::Error> {
+        self.with_store(|store| store.remove_session(session_id));
+        Ok(())
+    }
+
+    async fn read_value(&mut self, session_id: &str, key: &str) -> Result<Option<Vec<u8>>, Self::Error> {
+        Ok(self.with_store(|store| {
+            let data = store
+                .sessions
+                .get(session_id)
+                .and_then(|session| session.values.get(key))
+                .map(|value| value.data.clone());
+            if data.is_some() {
+                store.touch(session_id, key);
+            }
+            data
+        }))
+    }
+
+    async fn write_value(&mut self, session_id: &str, key: &str, value: &[u8]) -> Result<(), Self::Error> {
+        let timestamp = now()?;
+        self.with_store(|store| store.set(session_id, key, value.to_vec(), timestamp));
+        Ok(())
+    }
+
+    async fn remove_value(&mut self, session_id: &str, key: &str) -> Result<(), Self::Error> {
+        self.with_store(|store| store.remove(session_id, key));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionManager;
+
+    #[tokio::test]
+    async fn lru_eviction() {
+        let backend = MemoryBackend::new(2);
+        let session_manager = SessionManager::new(backend.clone());
+        let mut session1 = session_manager.get_session_by_raw_id("1");
+        let mut session2 = session_manager.get_session_by_raw_id("2");
+
+        session1.set("a", &1).await.unwrap();
+        session2.set("b", &2).await.unwrap();
+        // "a" becomes the most recently used value
+        assert_eq!(session1.get::<_, usize>("a").await.unwrap(), Some(1));
+        // eviction is per total number of values, so "b" is evicted from another session
+        session1.set("c", &3).await.unwrap();
+        assert_eq!(session2.get::<_, usize>("b").await.unwrap(), None);
+        assert_eq!(session1.get::<_, usize>("a").await.unwrap(), Some(1));
+        assert_eq!(session1.get::<_, usize>("c").await.unwrap(), Some(3));
+
+        // overwriting does not increase number of values
+        session1.set("c", &4).await.unwrap();
+        assert_eq!(session1.get::<_, usize>("a").await.unwrap(), Some(1));
+        assert_eq!(session1.get::<_, usize>("c").await.unwrap(), Some(4));
+
+        let mut backend = backend;
+        assert_eq!(backend.get_sessions().await.unwrap(), vec![String::from("1")]);
+        assert!(backend.get_session_age("1").await.unwrap().is_some());
+        assert!(backend.get_session_age("2").await.unwrap().is_none());
+    }
+
+    /// Returns data in a format written by session
+    fn create_value(value: usize, expires_at: Option<u64>) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({"expires_at": expires_at, "value": value})).unwrap()
+    }
+
+    #[tokio::test]
+    async fn stored_format() {
+        let mut backend = MemoryBackend::new(10);
+        let session_manager = SessionManager::new(backend.clone());
+        let mut session = session_manager.get_session_by_raw_id("1");
+
+        // fails when session changes a format of stored values
+        session.set("a", &1).await.unwrap();
+        let data = backend.read_value("1", "a").await.unwrap().unwrap();
+        assert_eq!(data, create_value(1, None));
+        assert_eq!(parse_expires_at(&data), Some(None));
+
+        session.expire("a", 3600).await.unwrap();
+        let data = backend.read_value("1", "a").await.unwrap().unwrap();
+        let expires_at = now().unwrap() + 3600;
+        let parsed = parse_expires_at(&data).unwrap().unwrap();
+        assert!(parsed <= expires_at && parsed + 1 >= expires_at);
+        assert_eq!(data, create_value(1, Some(parsed)));
+        assert_eq!(backend.with_store(|store| store.expiring.len()), 1);
+
+        assert_eq!(parse_expires_at(b"{\"value\": 1}"), None);
+        assert_eq!(parse_expires_at(b"{\"expires_at\": 1}"), None);
+        assert_eq!(parse_expires_at(b"1"), None);
+    }
+
+    #[tokio::test]
+    async fn expire_and_remove() {
+        let mut backend = MemoryBackend::new(10);
+        let session_manager = SessionManager::new(backend.clone());
+        let mut session = session_manager.get_session_by_raw_id("1");
+
+        backend.write_value("1", "a", &create_value(1, Some(1))).await.unwrap();
+        session.set("b", &2).await.unwrap();
+        assert_eq!(session.get::<_, usize>("a").await.unwrap(), None);
+
+        session.remove("b").await.unwrap();
+        assert_eq!(session.get::<_, usize>("b").await.unwrap(), None);
+
+        session.set("b", &2).await.unwrap();
+        backend.remove_session("1").await.unwrap();
+        assert_eq!(session.get::<_, usize>("b").await.unwrap(), None);
+        assert!(backend.get_sessions().await.unwrap().is_empty());
+        assert!(backend.with_store(|store| store.usage.is_empty() && store.expiring.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn evict_expired_first() {
+        let mut backend = MemoryBackend::new(2);
+        let session_manager = SessionManager::new(backend.clone());
+        let mut session = session_manager.get_session_by_raw_id("1");
+
+        session.set("a", &1).await.unwrap();
+        // "b" is the most recently used value, but it is expired
+        backend.write_value("1", "b", &create_value(2, Some(1))).await.unwrap();
+
+        session.set("c", &3).await.unwrap();
+        backend.with_store(|store| {
+            let values = &store.sessions["1"].values;
+            assert!(values.contains_key("a"));
+            assert!(!values.contains_key("b"));
+            assert!(values.contains_key("c"));
+            assert!(store.expiring.is_empty());
+        });
+    }
+
+    #[tokio::test]
+    async fn collect_expired() {
+        let mut backend = MemoryBackend::new(10);
+        let session_manager = SessionManager::new(backend.clone());
+        let mut session = session_manager.get_session_by_raw_id("1");
+
+        backend.write_value("1", "a", &create_value(1, Some(1))).await.unwrap();
+        session.set("b", &2).await.unwrap();
+        session.expire("b", 3600).await.unwrap();
+        session.set("c", &3).await.unwrap();
+        assert_eq!(backend.collect_expired().unwrap(), 1);
+        assert_eq!(backend.collect_expired().unwrap(), 0);
+
+        backend.write_value("1", "d", &create_value(4, Some(1))).await.unwrap();
+        let gc = backend.spawn_gc(Duration::from_millis(10));
+        tokio::time::delay_for(Duration::from_millis(50)).await;
+        backend.with_store(|store| {
+            let values = &store.sessions["1"].values;
+            assert!(!values.contains_key("a"));
+            assert!(values.contains_key("b"));
+            assert!(values.contains_key("c"));
+            assert!(!values.contains_key("d"));
+            assert_eq!(store.usage.len(), 2);
+            assert_eq!(store.expiring.len(), 1);
+        });
+
+        // task stops when backend is dropped
+        drop(session);
+        drop(session_manager);
+        drop(backend);
+        tokio::time::timeout(Duration::from_secs(1), gc).await.unwrap().unwrap();
+    }
+}
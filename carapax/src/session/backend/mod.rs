@@ -0,0 +1,4 @@
+mod memory;
+
+pub use self::memory::MemoryBackend;
+pub use seance::backend::*;
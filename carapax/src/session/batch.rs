@@ -0,0 +1,147 @@
+use crate::session::{Session, SessionError};
+use seance::backend::SessionBackend;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Buffers changes of a session until they are committed
+///
+/// Values set or removed using a batch are kept in memory,
+/// reads return pending changes before values stored in a session.
+/// Nothing is written to a session until [commit()](#method.commit) is called,
+/// so a batch can be simply dropped when a handler fails.
+///
+/// # Example
+///
+/// ```
+/// use carapax::session::{backend::SessionBackend, Session, SessionBatch, SessionError};
+///
+/// async fn increment<B>(session: Session<B>) -> Result<(), SessionError>
+/// where
+///     B: SessionBackend,
+/// {
+///     let mut batch = SessionBatch::new(session);
+///     let counter: usize = batch.get("counter").await?.unwrap_or(0);
+///     batch.set("counter", &(counter + 1))?;
+///     batch.remove("last_error");
+///     batch.commit().await
+/// }
+/// ```
+pub struct SessionBatch<B> {
+    session: Session<B>,
+    changes: HashMap<String, Option<Value>>,
+}
+
+impl<B> SessionBatch<B>
+where
+    B: SessionBackend,
+{
+    /// Creates a new batch
+    ///
+    /// # Arguments
+    ///
+    /// * session - Session to commit changes to
+    pub fn new(session: Session<B>) -> Self {
+        Self {
+            session,
+            changes: HashMap::new(),
+        }
+    }
+
+    /// Gets a value for key taking into account pending changes
+    pub async fn get<K, O>(&mut self, key: K) -> Result<Option<O>, SessionError>
+    where
+        K: AsRef<str>,
+        O: DeserializeOwned,
+    {
+        match self.changes.get(key.as_ref()) {
+            Some(Some(value)) => Ok(Some(
+                serde_json::from_value(value.clone()).map_err(SessionError::ParseValue)?,
+            )),
+            Some(None) => Ok(None),
+            None => self.session.get(key).await,
+        }
+    }
+
+    /// Sets a value for key
+    pub fn set<K, V>(&mut self, key: K, value: &V) -> Result<(), SessionError>
+    where
+        K: AsRef<str>,
+        V: Serialize,
+    {
+        let value = serde_json::to_value(value).map_err(SessionError::EncodeValue)?;
+        self.changes.insert(String::from(key.as_ref()), Some(value));
+        Ok(())
+    }
+
+    /// Removes a key
+    pub fn remove<K>(&mut self, key: K)
+    where
+        K: AsRef<str>,
+    {
+        self.changes.insert(String::from(key.as_ref()), None);
+    }
+
+    /// Returns true if there are no pending changes
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Writes all pending changes to the session
+    ///
+    /// Changes are written one key at a time in no particular order,
+    /// since session backends have no batch operations,
+    /// so a commit is not atomic: when a write fails, changes written before it are kept
+    /// and the rest are discarded.
+    /// Lifetime of an existing key is kept when a new value is set.
+    pub async fn commit(mut self) -> Result<(), SessionError> {
+        for (key, value) in self.changes.drain() {
+            match value {
+                Some(value) => self.session.set(key, &value).await?,
+                None => self.session.remove(key).await?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{backend::MemoryBackend, SessionManager};
+
+    #[tokio::test]
+    async fn commit() {
+        let manager = SessionManager::new(MemoryBackend::new(10));
+        let mut session = manager.get_session_by_raw_id("test");
+        session.set("removed", &1).await.unwrap();
+        session.set("kept", &2).await.unwrap();
+
+        let mut batch = SessionBatch::new(session.clone());
+        assert!(batch.is_empty());
+        batch.set("added", &"value").unwrap();
+        batch.remove("removed");
+        assert_eq!(batch.get::<_, String>("added").await.unwrap().unwrap(), "value");
+        assert!(batch.get::<_, usize>("removed").await.unwrap().is_none());
+        assert_eq!(batch.get::<_, usize>("kept").await.unwrap(), Some(2));
+
+        // nothing is written before commit
+        assert!(session.get::<_, String>("added").await.unwrap().is_none());
+        assert_eq!(session.get::<_, usize>("removed").await.unwrap(), Some(1));
+
+        batch.commit().await.unwrap();
+        assert_eq!(session.get::<_, String>("added").await.unwrap().unwrap(), "value");
+        assert!(session.get::<_, usize>("removed").await.unwrap().is_none());
+        assert_eq!(session.get::<_, usize>("kept").await.unwrap(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn discard() {
+        let manager = SessionManager::new(MemoryBackend::new(10));
+        let mut session = manager.get_session_by_raw_id("test");
+        let mut batch = SessionBatch::new(session.clone());
+        batch.set("key", &1).unwrap();
+        drop(batch);
+        assert!(session.get::<_, usize>("key").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::session::{Session, SessionError};
+use async_trait::async_trait;
+use seance::backend::SessionBackend;
+use serde::de::{DeserializeOwned, Error as _};
+
+/// Additional methods for a [Session](struct.Session.html)
+///
+/// # Example
+///
+/// ```
+/// use carapax::session::{backend::SessionBackend, Session, SessionError, SessionExt};
+///
+/// async fn get_counter<B>(session: &mut Session<B>) -> Result<usize, SessionError>
+/// where
+///     B: SessionBackend + Send,
+/// {
+///     session.get_or("counter").await
+/// }
+/// ```
+#[async_trait]
+pub trait SessionExt {
+    /// Gets a value for key or a default value when key is missing
+    ///
+    /// Default value is not stored.
+    /// A stored `null` is a present value, so it is parsed into `O`.
+    async fn get_or<K, O>(&mut self, key: K) -> Result<O, SessionError>
+    where
+        K: AsRef<str> + Send,
+        O: DeserializeOwned + Default + Send;
+
+    /// Gets a value for key or a value returned by a closure when key is missing
+    ///
+    /// Value returned by the closure is not stored.
+    /// A stored `null` is a present value, so it is parsed into `O`.
+    async fn get_or_else<K, O, F>(&mut self, key: K, f: F) -> Result<O, SessionError>
+    where
+        K: AsRef<str> + Send,
+        O: DeserializeOwned + Send,
+        F: FnOnce() -> O + Send;
+
+    /// Sets bytes for key
+    ///
+    /// Bytes are stored as a base64 string instead of a JSON array of numbers,
+    /// so a stored value is much smaller.
+    async fn set_bytes<K>(&mut self, key: K, value: &[u8]) -> Result<(), SessionError>
+    where
+        K: AsRef<str> + Send;
+
+    /// Gets bytes stored using [set_bytes()](#tymethod.set_bytes)
+    ///
+    /// Returns `SessionError::ParseValue` if a stored value is not a valid base64 string
+    async fn get_bytes<K>(&mut self, key: K) -> Result<Option<Vec<u8>>, SessionError>
+    where
+        K: AsRef<str> + Send;
+}
+
+#[async_trait]
+impl<B> SessionExt for Session<B>
+where
+    B: SessionBackend + Send,
+{
+    async fn get_or<K, O>(&mut self, key: K) -> Result<O, SessionError>
+    where
+        K: AsRef<str> + Send,
+        O: DeserializeOwned + Default + Send,
+    {
+        self.get_or_else(key, O::default).await
+    }
+
+    async fn get_or_else<K, O, F>(&mut self, key: K, f: F) -> Result<O, SessionError>
+    where
+        K: AsRef<str> + Send,
+        O: DeserializeOwned + Send,
+        F: FnOnce() -> O + Send,
+    {
+        Ok(self.get(key).await?.unwrap_or_else(f))
+    }
+
+    async fn set_bytes<K>(&mut self, key: K, value: &[u8]) -> Result<(), SessionError>
+    where
+        K: AsRef<str> + Send,
+    {
+        self.set(key, &base64::encode(value)).await
+    }
+
+    async fn get_bytes<K>(&mut self, key: K) -> Result<Option<Vec<u8>>, SessionError>
+    where
+        K: AsRef<str> + Send,
+    {
+        match self.get::<_, String>(key).await? {
+            Some(value) => match base64::decode(value) {
+                Ok(value) => Ok(Some(value)),
+                Err(err) => Err(SessionError::ParseValue(serde_json::Error::custom(format!(
+                    "failed to decode bytes: {}",
+                    err
+                )))),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{backend::MemoryBackend, SessionManager};
+
+    #[tokio::test]
+    async fn get_or() {
+        let manager = SessionManager::new(MemoryBackend::new(10));
+        let mut session = manager.get_session_by_raw_id("test");
+
+        assert_eq!(session.get_or::<_, usize>("missing").await.unwrap(), 0);
+        assert_eq!(session.get_or_else("missing", || 5usize).await.unwrap(), 5);
+        // default value is not stored
+        assert!(session.get::<_, usize>("missing").await.unwrap().is_none());
+
+        session.set("present", &2usize).await.unwrap();
+        assert_eq!(session.get_or::<_, usize>("present").await.unwrap(), 2);
+
+        // null is a present value
+        session.set("null", &()).await.unwrap();
+        assert_eq!(session.get_or_else("null", || Some(5usize)).await.unwrap(), None);
+        assert!(matches!(
+            session.get_or::<_, usize>("null").await,
+            Err(SessionError::ParseValue(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn bytes() {
+        let manager = SessionManager::new(MemoryBackend::new(10));
+        let mut session = manager.get_session_by_raw_id("test");
+
+        assert!(session.get_bytes("bytes").await.unwrap().is_none());
+        session.set_bytes("bytes", &[0, 1, 255]).await.unwrap();
+        assert_eq!(session.get_bytes("bytes").await.unwrap().unwrap(), vec![0, 1, 255]);
+        assert_eq!(session.get::<_, String>("bytes").await.unwrap().unwrap(), "AAH/");
+
+        session.set("invalid", &"*").await.unwrap();
+        assert!(matches!(
+            session.get_bytes("invalid").await,
+            Err(SessionError::ParseValue(_))
+        ));
+    }
+}
@@ -0,0 +1,320 @@
+use crate::session::{SessionError, SessionManager};
+use ring::digest;
+use seance::backend::SessionBackend;
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+const SESSION_ID: &str = "__carapax_idempotency";
+
+/// Runs an action at most once per key
+///
+/// Completed keys are stored in a session, so Telegram redeliveries
+/// of the same update (e.g. `successful_payment`) do not run the action again.
+/// Keys being processed are tracked in memory, so concurrent redeliveries
+/// handled by the same guard (or its clones) do not run the action twice.
+///
+/// Keys are hashed using SHA-256 before they are stored,
+/// so any string (e.g. an invoice payload) is a safe key for every backend.
+///
+/// # Durability
+///
+/// All keys are stored in the `__carapax_idempotency` session.
+/// A completed key is forgotten when the session is removed by a
+/// [SessionCollector](struct.SessionCollector.html) or evicted by a
+/// [MemoryBackend](backend/struct.MemoryBackend.html) which has reached its capacity.
+/// A redelivered update runs the action again after that,
+/// so use a session manager with a persistent backend which is not collected,
+/// e.g. a separate filesystem or redis backend.
+///
+/// # Example
+///
+/// ```
+/// use carapax::{
+///     session::{backend::SessionBackend, IdempotencyError, IdempotencyGuard},
+///     types::{Message, MessageData},
+/// };
+/// use std::convert::Infallible;
+///
+/// async fn handle_payment<B>(guard: &IdempotencyGuard<B>, message: Message) -> Result<(), IdempotencyError<Infallible>>
+/// where
+///     B: SessionBackend + Send,
+/// {
+///     if let MessageData::SuccessfulPayment(ref payment) = message.data {
+///         guard
+///             .once(&payment.invoice_payload, || async {
+///                 // deliver goods here
+///                 Ok::<(), Infallible>(())
+///             })
+///             .await?;
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct IdempotencyGuard<B> {
+    session_manager: SessionManager<B>,
+    lifetime: Option<u64>,
+    running: Arc<Mutex<HashSet<String>>>,
+}
+
+impl<B> IdempotencyGuard<B>
+where
+    B: SessionBackend,
+{
+    /// Creates a new guard
+    ///
+    /// # Arguments
+    ///
+    /// * session_manager - Session manager to store completed keys
+    pub fn new(session_manager: SessionManager<B>) -> Self {
+        Self {
+            session_manager,
+            lifetime: None,
+            running: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Sets lifetime of a completed key in seconds
+    ///
+    /// Keys are kept forever by default
+    pub fn lifetime(mut self, seconds: u64) -> Self {
+        self.lifetime = Some(seconds);
+        self
+    }
+
+    /// Runs an action if given key was not seen before
+    ///
+    /// Key is recorded as completed only when the action returns `Ok`,
+    /// so a failed action can be retried.
+    /// Returns None if key is already completed.
+    ///
+    /// [IdempotencyError::InProgress](enum.IdempotencyError.html#variant.InProgress)
+    /// is returned when another call with the same key is running.
+    /// That call may fail, so retry later instead of dropping an update.
+    ///
+    /// [IdempotencyError::NotRecorded](enum.IdempotencyError.html#variant.NotRecorded)
+    /// is returned when the action has completed, but the key could not be stored.
+    /// Do not retry the action in this case: it is not protected from a redelivery.
+    pub async fn once<F, Fut, T, E>(&self, key: &str, f: F) -> Result<Option<T>, IdempotencyError<E>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let key = hash_key(key);
+        let _running = match RunningKey::acquire(&self.running, &key) {
+            Some(running) => running,
+            None => return Err(IdempotencyError::InProgress),
+        };
+        let mut session = self.session_manager.get_session_by_raw_id(SESSION_ID);
+        if session
+            .get::<_, bool>(&key)
+            .await
+            .map_err(IdempotencyError::Session)?
+            .unwrap_or(false)
+        {
+            return Ok(None);
+        }
+        let result = f().await.map_err(IdempotencyError::Action)?;
+        session.set(&key, &true).await.map_err(IdempotencyError::NotRecorded)?;
+        if let Some(lifetime) = self.lifetime {
+            session
+                .expire(&key, lifetime)
+                .await
+                .map_err(IdempotencyError::NotRecorded)?;
+        }
+        Ok(Some(result))
+    }
+}
+
+fn hash_key(key: &str) -> String {
+    digest::digest(&digest::SHA256, key.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// An error returned by [IdempotencyGuard::once()](struct.IdempotencyGuard.html#method.once)
+#[derive(Debug)]
+pub enum IdempotencyError<E> {
+    /// Another call with the same key is running, the action was not run
+    InProgress,
+    /// Failed to check whether a key is completed, the action was not run
+    Session(SessionError),
+    /// The action has failed, the key is not recorded
+    Action(E),
+    /// The action has completed, but the key was not recorded
+    NotRecorded(SessionError),
+}
+
+impl<E> Error for IdempotencyError<E>
+where
+    E: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            IdempotencyError::InProgress => None,
+            IdempotencyError::Session(err) => Some(err),
+            IdempotencyError::Action(err) => Some(err),
+            IdempotencyError::NotRecorded(err) => Some(err),
+        }
+    }
+}
+
+impl<E> fmt::Display for IdempotencyError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdempotencyError::InProgress => write!(out, "action with the same idempotency key is running"),
+            IdempotencyError::Session(err) => write!(out, "failed to check idempotency key: {}", err),
+            IdempotencyError::Action(err) => write!(out, "{}", err),
+            IdempotencyError::NotRecorded(err) => {
+                write!(
+                    out,
+                    "action has completed, but idempotency key was not recorded: {}",
+                    err
+                )
+            }
+        }
+    }
+}
+
+/// Removes a key from running keys when dropped
+struct RunningKey<'a> {
+    running: &'a Mutex<HashSet<String>>,
+    key: String,
+}
+
+impl<'a> RunningKey<'a> {
+    fn acquire(running: &'a Mutex<HashSet<String>>, key: &str) -> Option<Self> {
+        let key = String::from(key);
+        let mut keys = running.lock().expect("Idempotency guard lock poisoned");
+        if keys.insert(key.clone()) {
+            Some(Self { running, key })
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> Drop for RunningKey<'a> {
+    fn drop(&mut self) {
+        if let Ok(mut keys) = self.running.lock() {
+            keys.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::backend::MemoryBackend;
+    use async_trait::async_trait;
+    use futures_util::future::join;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::oneshot;
+
+    /// A backend which stores nothing
+    struct FailingBackend;
+
+    #[async_trait]
+    impl SessionBackend for FailingBackend {
+        type Error = std::fmt::Error;
+
+        async fn get_sessions(&mut self) -> Result<Vec<String>, Self::Error> {
+            Ok(Vec::new())
+        }
+
+        async fn get_session_age(&mut self, _session_id: &str) -> Result<Option<u64>, Self::Error> {
+            Ok(None)
+        }
+
+        async fn remove_session(&mut self, _session_id: &str) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        async fn read_value(&mut self, _session_id: &str, _key: &str) -> Result<Option<Vec<u8>>, Self::Error> {
+            Ok(None)
+        }
+
+        async fn write_value(&mut self, _session_id: &str, _key: &str, _value: &[u8]) -> Result<(), Self::Error> {
+            Err(std::fmt::Error)
+        }
+
+        async fn remove_value(&mut self, _session_id: &str, _key: &str) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn once() {
+        let guard = IdempotencyGuard::new(SessionManager::new(MemoryBackend::new(10)));
+        let calls = AtomicUsize::new(0);
+        let run = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, SessionError>(1)
+        };
+
+        assert_eq!(guard.once("key", run).await.unwrap(), Some(1));
+        assert_eq!(guard.once("key", run).await.unwrap(), None);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // failed action is not recorded
+        let result = guard.once("failed", || async { Err::<(), _>(std::fmt::Error) }).await;
+        assert!(matches!(result, Err(IdempotencyError::Action(_))));
+        assert_eq!(guard.once("failed", run).await.unwrap(), Some(1));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn hashed_key() {
+        let manager = SessionManager::new(MemoryBackend::new(10));
+        let guard = IdempotencyGuard::new(manager.clone());
+        let key = "../../payload";
+        guard.once(key, || async { Ok::<_, SessionError>(()) }).await.unwrap();
+        let mut session = manager.get_session_by_raw_id(SESSION_ID);
+        assert!(session.get::<_, bool>(key).await.unwrap().is_none());
+        let hashed = hash_key(key);
+        assert_eq!(hashed.len(), 64);
+        assert!(hashed.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(session.get::<_, bool>(hashed).await.unwrap(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn not_recorded() {
+        let manager = SessionManager::new(FailingBackend);
+        let guard = IdempotencyGuard::new(manager);
+        let result = guard.once("key", || async { Ok::<_, SessionError>(1) }).await;
+        assert!(matches!(result, Err(IdempotencyError::NotRecorded(_))));
+    }
+
+    #[tokio::test]
+    async fn concurrent() {
+        let guard = IdempotencyGuard::new(SessionManager::new(MemoryBackend::new(10)));
+        let (tx, rx) = oneshot::channel::<()>();
+        let first = guard.once("key", || async {
+            rx.await.unwrap();
+            Err::<usize, _>(std::fmt::Error)
+        });
+        let second = async {
+            // first action is still running
+            let result = guard.once("key", || async { Ok::<_, std::fmt::Error>(2) }).await;
+            tx.send(()).unwrap();
+            result
+        };
+        let (first, second) = join(first, second).await;
+        assert!(matches!(first, Err(IdempotencyError::Action(_))));
+        assert!(matches!(second, Err(IdempotencyError::InProgress)));
+
+        // first action has failed, so a retry runs the action
+        let retry = guard.once("key", || async { Ok::<_, std::fmt::Error>(2) }).await;
+        assert_eq!(retry.unwrap(), Some(2));
+    }
+}
@@ -1,19 +1,43 @@
 use seance::backend::SessionBackend;
+use serde::{de::DeserializeOwned, Serialize};
 use std::{
+    collections::HashMap,
     convert::{TryFrom, TryInto},
     error::Error,
     fmt,
+    sync::{Arc, Mutex as SyncMutex, Weak},
 };
-use tgbot::types::{Command, Integer, Message, Update};
+use tgbot::types::{Command, Integer, Message, Update, UpdateKind};
+use tokio::sync::Mutex;
 
 pub use seance::{
-    backend, Session, SessionCollector, SessionCollectorHandle, SessionError, SessionManager as BaseSessionManager,
+    Session, SessionCollector, SessionCollectorHandle, SessionError, SessionManager as BaseSessionManager,
 };
 
+/// Store backend implementations
+pub mod backend;
+
+mod batch;
+mod ext;
+mod idempotency;
+
+pub use self::{
+    batch::SessionBatch,
+    ext::SessionExt,
+    idempotency::{IdempotencyError, IdempotencyGuard},
+};
+
+const PING_SESSION_ID: &str = "__carapax_ping";
+const PING_KEY: &str = "ping";
+
+type UpdateLock = Arc<Mutex<()>>;
+type UpdateLocks = HashMap<(String, String), Weak<Mutex<()>>>;
+
 /// A session manager
 #[derive(Clone)]
 pub struct SessionManager<B> {
     inner: BaseSessionManager<B>,
+    update_locks: Arc<SyncMutex<UpdateLocks>>,
 }
 
 impl<B> SessionManager<B>
@@ -28,6 +52,7 @@ where
     pub fn new(backend: B) -> Self {
         Self {
             inner: BaseSessionManager::new(backend),
+            update_locks: Arc::new(SyncMutex::new(HashMap::new())),
         }
     }
 
@@ -48,6 +73,58 @@ where
     {
         self.inner.get_session(id.as_ref())
     }
+
+    /// Checks whether a session backend is available
+    ///
+    /// A reserved key is read from a reserved session,
+    /// so use this method in a readiness probe to detect a broken backend early.
+    pub async fn ping(&self) -> Result<(), SessionError> {
+        let mut session = self.inner.get_session(PING_SESSION_ID);
+        session.get::<_, bool>(PING_KEY).await?;
+        Ok(())
+    }
+
+    /// Atomically updates a value for key
+    ///
+    /// Closure receives a current value and returns a new one, which is stored and returned.
+    /// Updates of the same key made using this method (by the same manager or its clones) never interleave,
+    /// so concurrent read-modify-write operations do not lose changes.
+    /// Updates of different keys do not wait for each other.
+    /// The guarantee does not cover `Session::set()` calls and other processes sharing a backend.
+    ///
+    /// # Arguments
+    ///
+    /// * session_id - Raw ID of a session
+    /// * key - Key to update
+    /// * f - Returns a new value
+    pub async fn update<I, K, O, F>(&self, session_id: I, key: K, f: F) -> Result<O, SessionError>
+    where
+        I: AsRef<str>,
+        K: AsRef<str>,
+        O: DeserializeOwned + Serialize,
+        F: FnOnce(Option<O>) -> O,
+    {
+        let (session_id, key) = (session_id.as_ref(), key.as_ref());
+        let lock = self.get_update_lock(session_id, key);
+        let _guard = lock.lock().await;
+        let mut session = self.inner.get_session(session_id);
+        let value = f(session.get(key).await?);
+        session.set(key, &value).await?;
+        Ok(value)
+    }
+
+    fn get_update_lock(&self, session_id: &str, key: &str) -> UpdateLock {
+        let mut locks = self.update_locks.lock().expect("Update locks poisoned");
+        let lock_key = (String::from(session_id), String::from(key));
+        if let Some(lock) = locks.get(&lock_key).and_then(Weak::upgrade) {
+            return lock;
+        }
+        // only locks of running updates are kept
+        locks.retain(|_, lock| lock.strong_count() > 0);
+        let lock = Arc::new(Mutex::new(()));
+        locks.insert(lock_key, Arc::downgrade(&lock));
+        lock
+    }
 }
 
 /// Session ID obtained from Update, Message, etc...
@@ -63,17 +140,55 @@ impl SessionId {
     pub fn new(chat_id: Integer, user_id: Integer) -> Self {
         Self(format!("{}-{}", chat_id, user_id))
     }
+
+    /// Creates a session ID shared by all users of a chat
+    ///
+    /// # Arguments
+    ///
+    /// * chat_id - Unique ID of chat
+    pub fn chat(chat_id: Integer) -> Self {
+        Self(format!("chat:{}", chat_id))
+    }
+
+    /// Creates a session ID shared by all chats of a user
+    ///
+    /// # Arguments
+    ///
+    /// * user_id - Unique ID of user
+    pub fn user(user_id: Integer) -> Self {
+        Self(format!("user:{}", user_id))
+    }
+
+    /// Parses a session ID in `chat_id-user_id` format
+    ///
+    /// Returns None if a string is not a valid session ID.
+    /// IDs created using [chat()](#method.chat) and [user()](#method.user) are not parsed.
+    /// Use it when you need to recover a session ID from a backend key.
+    pub fn parse(id: &str) -> Option<Self> {
+        // chat ID could be negative, so split on the last separator
+        let idx = id.rfind('-')?;
+        let (chat_id, user_id) = (&id[..idx], &id[idx + 1..]);
+        Some(Self::new(chat_id.parse().ok()?, user_id.parse().ok()?))
+    }
+}
+
+impl AsRef<str> for SessionId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        write!(out, "{}", self.0)
+    }
 }
 
 impl TryFrom<&Update> for SessionId {
     type Error = SessionIdError;
 
     fn try_from(update: &Update) -> Result<Self, Self::Error> {
-        if let (Some(chat_id), Some(user_id)) = (update.get_chat_id(), update.get_user().map(|x| x.id)) {
-            Ok(SessionId::new(chat_id, user_id))
-        } else {
-            Err(SessionIdError)
-        }
+        SessionIdStrategy::UserChat.for_update(update)
     }
 }
 
@@ -81,11 +196,7 @@ impl TryFrom<&Message> for SessionId {
     type Error = SessionIdError;
 
     fn try_from(message: &Message) -> Result<Self, Self::Error> {
-        if let (chat_id, Some(user_id)) = (message.get_chat_id(), message.get_user().map(|x| x.id)) {
-            Ok(SessionId::new(chat_id, user_id))
-        } else {
-            Err(SessionIdError)
-        }
+        SessionIdStrategy::UserChat.for_message(message)
     }
 }
 
@@ -97,6 +208,74 @@ impl TryFrom<&Command> for SessionId {
     }
 }
 
+/// Defines which IDs are used to obtain a session ID from an update
+///
+/// # Example
+///
+/// ```
+/// use carapax::{
+///     session::{backend::SessionBackend, SessionIdStrategy, SessionManager},
+///     types::Message,
+/// };
+/// use std::error::Error;
+///
+/// async fn get_language<B>(manager: &SessionManager<B>, message: &Message) -> Result<Option<String>, Box<dyn Error>>
+/// where
+///     B: SessionBackend,
+/// {
+///     // all users of a chat share the same session
+///     let mut session = manager.get_session(SessionIdStrategy::Chat.for_message(message)?)?;
+///     Ok(session.get("language").await?)
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SessionIdStrategy {
+    /// A session per user in a chat
+    ///
+    /// This strategy is used by default and when a session ID is obtained using `TryFrom`
+    UserChat,
+    /// A session per chat, see [SessionId::chat()](struct.SessionId.html#method.chat)
+    Chat,
+    /// A session per user, see [SessionId::user()](struct.SessionId.html#method.user)
+    User,
+}
+
+// #[default] on a variant requires Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for SessionIdStrategy {
+    fn default() -> Self {
+        SessionIdStrategy::UserChat
+    }
+}
+
+impl SessionIdStrategy {
+    /// Returns a session ID for an update
+    ///
+    /// A chat of a callback query is a chat of the message with the button.
+    /// Inline queries have no chat, so only `User` strategy works for them.
+    pub fn for_update(self, update: &Update) -> Result<SessionId, SessionIdError> {
+        let chat_id = match update.kind {
+            UpdateKind::CallbackQuery(ref query) => query.message.as_ref().map(|message| message.get_chat_id()),
+            _ => update.get_chat_id(),
+        };
+        self.create(chat_id, update.get_user().map(|user| user.id))
+    }
+
+    /// Returns a session ID for a message
+    pub fn for_message(self, message: &Message) -> Result<SessionId, SessionIdError> {
+        self.create(Some(message.get_chat_id()), message.get_user().map(|user| user.id))
+    }
+
+    fn create(self, chat_id: Option<Integer>, user_id: Option<Integer>) -> Result<SessionId, SessionIdError> {
+        match (self, chat_id, user_id) {
+            (SessionIdStrategy::UserChat, Some(chat_id), Some(user_id)) => Ok(SessionId::new(chat_id, user_id)),
+            (SessionIdStrategy::Chat, Some(chat_id), _) => Ok(SessionId::chat(chat_id)),
+            (SessionIdStrategy::User, _, Some(user_id)) => Ok(SessionId::user(user_id)),
+            _ => Err(SessionIdError),
+        }
+    }
+}
+
 /// Session ID could not be created from update
 ///
 /// This error happens when a received update
@@ -113,3 +292,138 @@ impl fmt::Display for SessionIdError {
         write!(out, "Could not obtain a session ID from update")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    fn create_update(kind: &str, chat: serde_json::Value, user: Option<serde_json::Value>) -> Update {
+        let mut message = serde_json::json!({
+            "message_id": 1111,
+            "date": 0,
+            "chat": chat,
+            "text": "test"
+        });
+        if let Some(user) = user {
+            message["from"] = user;
+        }
+        let mut update = serde_json::json!({"update_id": 1});
+        update[kind] = message;
+        serde_json::from_value(update).unwrap()
+    }
+
+    #[tokio::test]
+    async fn ping() {
+        let manager = SessionManager::new(backend::MemoryBackend::new(1));
+        manager.ping().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn update() {
+        let manager = SessionManager::new(backend::MemoryBackend::new(1));
+        let updates = (0..100).map(|_| {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                manager
+                    .update("test", "counter", |value: Option<usize>| value.unwrap_or(0) + 1)
+                    .await
+                    .unwrap();
+            })
+        });
+        for result in futures_util::future::join_all(updates).await {
+            result.unwrap();
+        }
+        let mut session = manager.get_session_by_raw_id("test");
+        assert_eq!(session.get::<_, usize>("counter").await.unwrap(), Some(100));
+        assert!(manager
+            .update_locks
+            .lock()
+            .unwrap()
+            .values()
+            .all(|lock| lock.strong_count() == 0));
+    }
+
+    #[tokio::test]
+    async fn update_different_keys() {
+        let manager = SessionManager::new(backend::MemoryBackend::new(1));
+        let lock = manager.get_update_lock("test", "first");
+        let _guard = lock.lock().await;
+        let increment = |value: Option<usize>| value.unwrap_or(0) + 1;
+
+        // another key is not locked
+        let result = timeout(Duration::from_secs(5), manager.update("test", "second", increment)).await;
+        assert_eq!(result.unwrap().unwrap(), 1);
+        let result = timeout(Duration::from_secs(5), manager.update("other", "first", increment)).await;
+        assert_eq!(result.unwrap().unwrap(), 1);
+
+        // same key waits
+        let result = timeout(Duration::from_millis(50), manager.update("test", "first", increment)).await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_session_id() {
+        for (chat_id, user_id) in &[(1, 2), (-1_001_234_567_890i64, 2)] {
+            let id = SessionId::new(*chat_id, *user_id).to_string();
+            assert_eq!(SessionId::parse(&id).unwrap().as_ref(), id);
+        }
+        for id in &["", "1", "1-", "-2", "a-2", "1-b"] {
+            assert!(SessionId::parse(id).is_none());
+        }
+    }
+
+    #[test]
+    fn session_id_strategy() {
+        let user = serde_json::json!({"id": 2, "is_bot": false, "first_name": "test"});
+        let message = serde_json::json!({
+            "message_id": 1111,
+            "date": 0,
+            "from": user,
+            "chat": {"id": -1, "type": "group", "title": "test", "all_members_are_administrators": false},
+            "text": "test"
+        });
+        let updates: Vec<Update> = vec![
+            serde_json::from_value(serde_json::json!({"update_id": 1, "message": message})).unwrap(),
+            serde_json::from_value(serde_json::json!({
+                "update_id": 1,
+                "callback_query": {"id": "id", "from": user, "chat_instance": "instance", "message": message}
+            }))
+            .unwrap(),
+        ];
+        for update in &updates {
+            let id = |strategy: SessionIdStrategy| strategy.for_update(update).unwrap().0;
+            assert_eq!(SessionId::try_from(update).unwrap().0, "-1-2");
+            assert_eq!(id(SessionIdStrategy::UserChat), "-1-2");
+            assert_eq!(id(SessionIdStrategy::Chat), "chat:-1");
+            assert_eq!(id(SessionIdStrategy::User), "user:2");
+        }
+
+        let inline_query: Update = serde_json::from_value(serde_json::json!({
+            "update_id": 1,
+            "inline_query": {"id": "id", "from": user, "query": "query", "offset": ""}
+        }))
+        .unwrap();
+        assert!(SessionIdStrategy::UserChat.for_update(&inline_query).is_err());
+        assert!(SessionIdStrategy::Chat.for_update(&inline_query).is_err());
+        assert_eq!(SessionIdStrategy::User.for_update(&inline_query).unwrap().0, "user:2");
+    }
+
+    #[test]
+    fn session_id_from_edited_update() {
+        let chat = serde_json::json!({"id": 1, "type": "private", "first_name": "test"});
+        let user = serde_json::json!({"id": 2, "is_bot": false, "first_name": "test"});
+        for kind in &["message", "edited_message"] {
+            let update = create_update(kind, chat.clone(), Some(user.clone()));
+            assert_eq!(SessionId::try_from(&update).unwrap().0, "1-2");
+        }
+
+        // channel posts have no author, so a session is not available
+        let chat = serde_json::json!({"id": -1_001_234_567_890i64, "type": "channel", "title": "test"});
+        for kind in &["channel_post", "edited_channel_post"] {
+            let update = create_update(kind, chat.clone(), None);
+            assert!(SessionId::try_from(&update).is_err());
+        }
+    }
+}
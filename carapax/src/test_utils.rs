@@ -0,0 +1,127 @@
+use futures_util::future::{ready, BoxFuture, FutureExt};
+use hyper::{
+    body::to_bytes,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+use serde_json::{json, Value};
+use std::{
+    convert::Infallible,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use tgbot::{Api, Config};
+use tokio::sync::mpsc;
+
+type Respond = dyn Fn(String, Value) -> BoxFuture<'static, Value> + Send + Sync;
+
+/// A request received by [MockServer]
+#[derive(Clone, Debug)]
+pub(crate) struct MockRequest {
+    /// Name of an API method
+    pub(crate) method: String,
+    /// JSON body, `Null` for empty and multipart bodies
+    pub(crate) body: Value,
+}
+
+/// A local Bot API server which records requests
+pub(crate) struct MockServer {
+    api: Api,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    received: mpsc::UnboundedReceiver<()>,
+}
+
+impl MockServer {
+    /// Starts a server which responds to every request with an error
+    pub(crate) fn with_error() -> Self {
+        Self::new(|_, _| error_response())
+    }
+
+    /// Starts a server which responds using a closure
+    ///
+    /// The closure receives a method name and a JSON body and returns a whole response.
+    pub(crate) fn new<F>(respond: F) -> Self
+    where
+        F: Fn(&str, &Value) -> Value + Send + Sync + 'static,
+    {
+        Self::with_async(move |method, body| ready(respond(&method, &body)))
+    }
+
+    /// Starts a server which responds using an async closure
+    ///
+    /// Use it to hold a response until a test does something.
+    pub(crate) fn with_async<F, Fut>(respond: F) -> Self
+    where
+        F: Fn(String, Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Value> + Send + 'static,
+    {
+        let respond: Arc<Respond> = Arc::new(move |method, body| respond(method, body).boxed());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let (sender, received) = mpsc::unbounded_channel();
+        let server_requests = requests.clone();
+        let make_service = make_service_fn(move |_| {
+            let respond = respond.clone();
+            let requests = server_requests.clone();
+            let sender = sender.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let respond = respond.clone();
+                    let requests = requests.clone();
+                    let sender = sender.clone();
+                    async move {
+                        let method = request.uri().path().rsplit('/').next().unwrap_or("").to_string();
+                        let body = to_bytes(request.into_body()).await.unwrap();
+                        let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                        requests.lock().unwrap().push(MockRequest {
+                            method: method.clone(),
+                            body: body.clone(),
+                        });
+                        let _ = sender.send(());
+                        let response = respond(method, body).await;
+                        Ok::<_, Infallible>(Response::new(Body::from(response.to_string())))
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let host = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+        Self {
+            api: Api::new(Config::new("token").host(host)).unwrap(),
+            requests,
+            received,
+        }
+    }
+
+    /// Returns a client which sends requests to the server
+    pub(crate) fn api(&self) -> Api {
+        self.api.clone()
+    }
+
+    /// Returns all received requests
+    pub(crate) fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Returns names of methods of all received requests
+    pub(crate) fn methods(&self) -> Vec<String> {
+        self.requests().into_iter().map(|request| request.method).collect()
+    }
+
+    /// Waits until given number of requests is received
+    pub(crate) async fn wait_requests(&mut self, count: usize) {
+        while self.requests.lock().unwrap().len() < count {
+            self.received.recv().await.unwrap();
+        }
+    }
+}
+
+/// Returns a successful response
+pub(crate) fn ok_response(result: Value) -> Value {
+    json!({"ok": true, "result": result})
+}
+
+/// Returns an error response
+pub(crate) fn error_response() -> Value {
+    json!({"ok": false, "error_code": 400, "description": "Bad Request: test"})
+}
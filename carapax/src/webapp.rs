@@ -0,0 +1,260 @@
+use ring::hmac;
+use std::{
+    error::Error,
+    fmt,
+    time::{Duration, SystemTime, SystemTimeError},
+};
+use tgbot::types::{Integer, User};
+
+const SECRET_KEY: &[u8] = b"WebAppData";
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(86400);
+
+/// Validates `initData` received from a Web App using default max age (1 day)
+///
+/// See [WebAppInitDataValidator](struct.WebAppInitDataValidator.html) for more information.
+///
+/// # Arguments
+///
+/// * init_data - Raw `Telegram.WebApp.initData` string
+/// * bot_token - Token of the bot which opened the Web App
+pub fn validate_web_app_init_data(init_data: &str, bot_token: &str) -> Result<WebAppInitData, ValidationError> {
+    WebAppInitDataValidator::new(bot_token).validate(init_data)
+}
+
+/// Validates `initData` received from a Web App
+///
+/// Data is trusted only when its `hash` matches HMAC-SHA-256 signature
+/// computed using a bot token, as described in Telegram documentation.
+///
+/// # Example
+///
+/// ```
+/// use carapax::webapp::{ValidationError, WebAppInitDataValidator};
+/// use std::time::Duration;
+///
+/// fn get_user_id(init_data: &str) -> Result<Option<i64>, ValidationError> {
+///     let validator = WebAppInitDataValidator::new("bot-token").max_age(Some(Duration::from_secs(3600)));
+///     let data = validator.validate(init_data)?;
+///     Ok(data.user.map(|user| user.id))
+/// }
+/// ```
+pub struct WebAppInitDataValidator {
+    key: hmac::Key,
+    max_age: Option<Duration>,
+}
+
+impl WebAppInitDataValidator {
+    /// Creates a new validator
+    ///
+    /// # Arguments
+    ///
+    /// * bot_token - Token of the bot which opened the Web App
+    pub fn new(bot_token: &str) -> Self {
+        let secret = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, SECRET_KEY), bot_token.as_bytes());
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret.as_ref()),
+            max_age: Some(DEFAULT_MAX_AGE),
+        }
+    }
+
+    /// Sets max age of data, calculated using `auth_date`
+    ///
+    /// Default value is 1 day, pass None in order to accept data of any age
+    pub fn max_age(mut self, value: Option<Duration>) -> Self {
+        self.max_age = value;
+        self
+    }
+
+    /// Validates data
+    ///
+    /// # Arguments
+    ///
+    /// * init_data - Raw `Telegram.WebApp.initData` string
+    pub fn validate(&self, init_data: &str) -> Result<WebAppInitData, ValidationError> {
+        let mut hash = None;
+        let mut fields = Vec::new();
+        for (key, value) in form_urlencoded::parse(init_data.as_bytes()) {
+            if key == "hash" {
+                hash = Some(value.into_owned());
+            } else {
+                fields.push((key.into_owned(), value.into_owned()));
+            }
+        }
+        let hash = hash.ok_or(ValidationError::HashMissing)?;
+        let hash = decode_hex(&hash).ok_or(ValidationError::HashMismatch)?;
+        fields.sort();
+        let data_check_string = fields
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>()
+            .join("\n");
+        hmac::verify(&self.key, data_check_string.as_bytes(), &hash).map_err(|_| ValidationError::HashMismatch)?;
+
+        let mut data = WebAppInitData {
+            auth_date: 0,
+            query_id: None,
+            user: None,
+            start_param: None,
+        };
+        let mut auth_date = None;
+        for (key, value) in fields {
+            match key.as_str() {
+                "auth_date" => auth_date = Some(value),
+                "query_id" => data.query_id = Some(value),
+                "user" => data.user = Some(serde_json::from_str(&value).map_err(ValidationError::User)?),
+                "start_param" => data.start_param = Some(value),
+                _ => {}
+            }
+        }
+        data.auth_date = auth_date
+            .and_then(|value| value.parse().ok())
+            .ok_or(ValidationError::AuthDateMissing)?;
+
+        if let Some(max_age) = self.max_age {
+            let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?.as_secs();
+            let age = now.saturating_sub(data.auth_date as u64);
+            if age > max_age.as_secs() {
+                return Err(ValidationError::Expired(data.auth_date));
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    let chunks = value.as_bytes().chunks_exact(2);
+    if !chunks.remainder().is_empty() {
+        return None;
+    }
+    chunks
+        .map(|chunk| {
+            std::str::from_utf8(chunk)
+                .ok()
+                .and_then(|chunk| u8::from_str_radix(chunk, 16).ok())
+        })
+        .collect()
+}
+
+/// Validated data received from a Web App
+#[derive(Clone, Debug)]
+pub struct WebAppInitData {
+    /// Unix time when the Web App was opened
+    pub auth_date: Integer,
+    /// Unique identifier of the Web App session
+    ///
+    /// Required in order to send a message via `answerWebAppQuery`
+    pub query_id: Option<String>,
+    /// User who opened the Web App
+    pub user: Option<User>,
+    /// Value of the `startattach` parameter passed via link
+    pub start_param: Option<String>,
+}
+
+/// An error when validating Web App data
+#[derive(Debug)]
+pub enum ValidationError {
+    /// Data is older than allowed max age
+    ///
+    /// Contains `auth_date` value
+    Expired(Integer),
+    /// Field `auth_date` is missing or is not a number
+    AuthDateMissing,
+    /// Field `hash` is missing
+    HashMissing,
+    /// Field `hash` does not match the data
+    HashMismatch,
+    /// Unable to get current time
+    SystemTime(SystemTimeError),
+    /// Unable to parse `user` field
+    User(serde_json::Error),
+}
+
+impl From<SystemTimeError> for ValidationError {
+    fn from(err: SystemTimeError) -> Self {
+        ValidationError::SystemTime(err)
+    }
+}
+
+impl Error for ValidationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ValidationError::SystemTime(err) => Some(err),
+            ValidationError::User(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, out: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::Expired(auth_date) => write!(out, "data is expired, auth_date: {}", auth_date),
+            ValidationError::AuthDateMissing => write!(out, "auth_date is missing"),
+            ValidationError::HashMissing => write!(out, "hash is missing"),
+            ValidationError::HashMismatch => write!(out, "hash does not match"),
+            ValidationError::SystemTime(err) => write!(out, "unable to get current time: {}", err),
+            ValidationError::User(err) => write!(out, "unable to parse user: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "123456:ABC-token";
+    const INIT_DATA: &str = concat!(
+        "auth_date=1600000000&query_id=AAHdF6IQAAAAAN0XohDhrOrc",
+        "&user=%7B%22id%22%3A1%2C%22is_bot%22%3Afalse%2C%22first_name%22%3A%22Test%22%2C%22username%22%3A%22test%22%7D",
+        "&hash=c79c9d43f30ad84a9b7c8fc18452cb571f81daee114000fa5a1e0952c28ca4a6"
+    );
+
+    #[test]
+    fn validate_ok() {
+        let data = WebAppInitDataValidator::new(TOKEN)
+            .max_age(None)
+            .validate(INIT_DATA)
+            .unwrap();
+        assert_eq!(data.auth_date, 1_600_000_000);
+        assert_eq!(data.query_id.unwrap(), "AAHdF6IQAAAAAN0XohDhrOrc");
+        let user = data.user.unwrap();
+        assert_eq!(user.id, 1);
+        assert_eq!(user.username.unwrap(), "test");
+        assert!(data.start_param.is_none());
+    }
+
+    #[test]
+    fn validate_expired() {
+        match validate_web_app_init_data(INIT_DATA, TOKEN) {
+            Err(ValidationError::Expired(1_600_000_000)) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn validate_hash() {
+        let validator = WebAppInitDataValidator::new(TOKEN).max_age(None);
+        for init_data in &[
+            INIT_DATA.replace("1600000000", "1600000001"),
+            INIT_DATA.replace("hash=c7", "hash=d7"),
+            INIT_DATA.replace("hash=c7", "hash=x7"),
+        ] {
+            match validator.validate(init_data) {
+                Err(ValidationError::HashMismatch) => {}
+                result => panic!("Unexpected result: {:?}", result),
+            }
+        }
+        match WebAppInitDataValidator::new("654321:ABC-token")
+            .max_age(None)
+            .validate(INIT_DATA)
+        {
+            Err(ValidationError::HashMismatch) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+        match validator.validate("auth_date=1600000000") {
+            Err(ValidationError::HashMissing) => {}
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Checks that real update samples can be deserialized
+//!
+//! Each file in `tests/fixtures/updates` contains a single update.
+//! In order to reproduce a deserialization error just drop a new file into that directory.
+use carapax::{
+    methods::SendMessage,
+    types::{Update, UpdateKind},
+};
+use serde_json::Value;
+use std::{fs, path::PathBuf};
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/updates")
+}
+
+fn load_fixtures() -> Vec<(String, Value)> {
+    let mut fixtures = Vec::new();
+    for entry in fs::read_dir(fixtures_dir()).expect("Failed to read fixtures directory") {
+        let path = entry.expect("Failed to read fixture entry").path();
+        if path.extension().map(|x| x != "json").unwrap_or(true) {
+            continue;
+        }
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let data = fs::read(&path).unwrap_or_else(|err| panic!("Failed to read {}: {}", name, err));
+        let value = serde_json::from_slice(&data).unwrap_or_else(|err| panic!("Invalid JSON in {}: {}", name, err));
+        fixtures.push((name, value));
+    }
+    fixtures.sort_by(|a, b| a.0.cmp(&b.0));
+    fixtures
+}
+
+/// Returns a key and an object containing update data (message, inline_query, etc...)
+fn get_update_object(value: &Value) -> (&str, &Value) {
+    value
+        .as_object()
+        .and_then(|obj| obj.iter().find(|(key, _)| *key != "update_id"))
+        .map(|(key, value)| (key.as_str(), value))
+        .expect("Update kind not found")
+}
+
+fn get_kind_name(kind: &UpdateKind) -> &'static str {
+    match kind {
+        UpdateKind::Message(_) => "message",
+        UpdateKind::EditedMessage(_) => "edited_message",
+        UpdateKind::ChannelPost(_) => "channel_post",
+        UpdateKind::EditedChannelPost(_) => "edited_channel_post",
+        UpdateKind::InlineQuery(_) => "inline_query",
+        UpdateKind::ChosenInlineResult(_) => "chosen_inline_result",
+        UpdateKind::CallbackQuery(_) => "callback_query",
+        UpdateKind::ShippingQuery(_) => "shipping_query",
+        UpdateKind::PreCheckoutQuery(_) => "pre_checkout_query",
+        UpdateKind::Poll(_) => "poll",
+        UpdateKind::PollAnswer(_) => "poll_answer",
+    }
+}
+
+#[test]
+fn deserialize_fixtures() {
+    let fixtures = load_fixtures();
+    assert!(!fixtures.is_empty());
+    for (name, value) in fixtures {
+        let update: Update = serde_json::from_value(value.clone())
+            .unwrap_or_else(|err| panic!("Failed to deserialize {}: {}", name, err));
+        assert_eq!(Some(update.id), value["update_id"].as_i64(), "update_id in {}", name);
+
+        let (kind, object) = get_update_object(&value);
+        assert_eq!(get_kind_name(&update.kind), kind, "update kind in {}", name);
+        if let Some(chat_id) = object["chat"]["id"].as_i64() {
+            assert_eq!(update.get_chat_id(), Some(chat_id), "chat ID in {}", name);
+        }
+        let user_id = object["from"]["id"].as_i64().or_else(|| object["user"]["id"].as_i64());
+        assert_eq!(update.get_user().map(|x| x.id), user_id, "user ID in {}", name);
+    }
+}
+
+#[test]
+fn large_chat_id() {
+    // supergroup and channel IDs exceed 32 bits and must not lose precision
+    let update: Update = serde_json::from_str(
+        r#"{
+            "update_id": 1,
+            "message": {
+                "message_id": 1111,
+                "date": 0,
+                "from": {"id": 1, "is_bot": false, "first_name": "test"},
+                "chat": {"id": -1001234567890, "type": "supergroup", "title": "test"},
+                "text": "test"
+            }
+        }"#,
+    )
+    .unwrap();
+    let update_chat_id = update.get_chat_id().unwrap();
+    assert_eq!(update_chat_id, -1_001_234_567_890);
+
+    let method = serde_json::to_string(&SendMessage::new(update_chat_id, "test")).unwrap();
+    assert!(method.contains(r#""chat_id":-1001234567890"#), "{}", method);
+}